@@ -0,0 +1,335 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::score::LevelResult;
+
+/// The worlds of the campaign and the levels in them, as stored in `assets/levels/main.campaign.ron`.
+///
+/// ```ron
+/// (
+///     worlds: [
+///         (
+///             name: "Parabolas",
+///             description: "Paths of degree 2.",
+///             levels: [
+///                 (file: "levels/level1_easy.level.ron"),
+///                 (file: "levels/level1_hard.level.ron", unlock: Stars(4)),
+///             ],
+///         ),
+///         (
+///             name: "Quartics",
+///             unlock: Stars(6),
+///             levels: [(file: "levels/level2_easy.level.ron")],
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3d8e6a1b-2f47-4c9a-b5e0-91c7d4f8a263"]
+pub struct Campaign {
+    pub worlds: Vec<CampaignWorld>,
+}
+
+/// A group of levels, e.g. of the same degree or curve family, that unlock one after the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignWorld {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// When the world can be entered, by default once the previous world is completed.
+    #[serde(default)]
+    pub unlock: Unlock,
+    pub levels: Vec<CampaignLevel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignLevel {
+    /// The level file, relative to the assets folder.
+    pub file: String,
+    /// When the level can be played, by default once the previous level of the world is won.
+    #[serde(default)]
+    pub unlock: Unlock,
+}
+
+/// What it takes to unlock a world or a level of the campaign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Unlock {
+    /// Unlocked from the start.
+    Always,
+    /// Unlocked once the previous level of the world is won,
+    /// or for a world, once every level of the previous world is won.
+    /// The first level of a world and the first world are always unlocked.
+    #[default]
+    Previous,
+    /// Unlocked once this many stars are earned in the whole campaign.
+    Stars(u32),
+}
+
+impl fmt::Display for Unlock {
+    /// Describes what the player needs to do to unlock it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unlock::Always => write!(f, "Always unlocked"),
+            Unlock::Previous => write!(f, "Complete the previous one"),
+            Unlock::Stars(stars) => write!(f, "Earn {} stars", stars),
+        }
+    }
+}
+
+/// How far the player got in a [`Campaign`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CampaignProgress {
+    /// The stars of the best results of all levels of the campaign.
+    pub stars: u32,
+    pub worlds: Vec<WorldProgress>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldProgress {
+    pub unlocked: bool,
+    /// Whether each level of the world is unlocked.
+    pub levels_unlocked: Vec<bool>,
+    /// The number of levels of the world that were won.
+    pub levels_won: usize,
+    pub stars: u32,
+}
+
+impl WorldProgress {
+    pub fn is_completed(&self) -> bool {
+        self.levels_won == self.levels_unlocked.len()
+    }
+}
+
+impl Campaign {
+    /// Works out what is unlocked from the best results of the levels.
+    /// Levels in `unlocked_levels` stay unlocked, even if the campaign has changed since.
+    pub fn progress(
+        &self,
+        best_results: &BTreeMap<String, LevelResult>,
+        unlocked_levels: &BTreeSet<String>,
+    ) -> CampaignProgress {
+        let is_won = |level: &CampaignLevel| best_results.get(&level.file).is_some_and(|r| r.won);
+        let stars_of = |levels: &[CampaignLevel]| -> u32 {
+            levels
+                .iter()
+                .filter_map(|level| best_results.get(&level.file))
+                .map(|result| result.stars as u32)
+                .sum()
+        };
+        let stars = self
+            .worlds
+            .iter()
+            .map(|world| stars_of(&world.levels))
+            .sum();
+        let is_unlocked = |unlock: Unlock, previous_done: Option<bool>| match unlock {
+            Unlock::Always => true,
+            Unlock::Previous => previous_done.unwrap_or(true),
+            Unlock::Stars(needed) => stars >= needed,
+        };
+
+        let mut worlds: Vec<WorldProgress> = Vec::with_capacity(self.worlds.len());
+        for world in &self.worlds {
+            let previous_done = worlds.last().map(WorldProgress::is_completed);
+            let unlocked = is_unlocked(world.unlock, previous_done)
+                || world
+                    .levels
+                    .iter()
+                    .any(|level| unlocked_levels.contains(&level.file));
+            let mut levels_unlocked = Vec::with_capacity(world.levels.len());
+            for (i, level) in world.levels.iter().enumerate() {
+                let previous_won = i.checked_sub(1).map(|i| is_won(&world.levels[i]));
+                levels_unlocked.push(
+                    unlocked
+                        && (is_unlocked(level.unlock, previous_won)
+                            || unlocked_levels.contains(&level.file)),
+                );
+            }
+            worlds.push(WorldProgress {
+                unlocked,
+                levels_unlocked,
+                levels_won: world.levels.iter().filter(|level| is_won(level)).count(),
+                stars: stars_of(&world.levels),
+            });
+        }
+        CampaignProgress { stars, worlds }
+    }
+
+    /// The level files that are part of the campaign.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.worlds
+            .iter()
+            .flat_map(|world| &world.levels)
+            .map(|level| level.file.as_str())
+    }
+}
+
+impl CampaignProgress {
+    /// The unlocked level files of the campaign.
+    pub fn unlocked_files<'a>(&'a self, campaign: &'a Campaign) -> impl Iterator<Item = &'a str> {
+        campaign
+            .worlds
+            .iter()
+            .zip(&self.worlds)
+            .flat_map(|(world, progress)| world.levels.iter().zip(&progress.levels_unlocked))
+            .filter(|(_, unlocked)| **unlocked)
+            .map(|(level, _)| level.file.as_str())
+    }
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let campaign = ron::de::from_bytes::<Campaign>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(campaign));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(file: &str, unlock: Unlock) -> CampaignLevel {
+        CampaignLevel {
+            file: file.to_string(),
+            unlock,
+        }
+    }
+
+    fn world(name: &str, unlock: Unlock, levels: Vec<CampaignLevel>) -> CampaignWorld {
+        CampaignWorld {
+            name: name.to_string(),
+            description: String::new(),
+            unlock,
+            levels,
+        }
+    }
+
+    fn campaign() -> Campaign {
+        Campaign {
+            worlds: vec![
+                world(
+                    "A",
+                    Unlock::Previous,
+                    vec![
+                        level("a1", Unlock::Previous),
+                        level("a2", Unlock::Previous),
+                        level("a3", Unlock::Stars(5)),
+                    ],
+                ),
+                world(
+                    "B",
+                    Unlock::Previous,
+                    vec![level("b1", Unlock::Previous), level("b2", Unlock::Always)],
+                ),
+                world("C", Unlock::Stars(4), vec![level("c1", Unlock::Previous)]),
+            ],
+        }
+    }
+
+    fn result(won: bool, stars: u8) -> LevelResult {
+        LevelResult {
+            won,
+            time_left: 0.0,
+            max_time: 60.0,
+            wrong_confirms: 0,
+            hints_used: 0,
+            error: 0.0,
+            tolerance: 0.1,
+            score: 0,
+            stars,
+        }
+    }
+
+    fn progress(results: &[(&str, LevelResult)], unlocked: &[&str]) -> CampaignProgress {
+        let results = results
+            .iter()
+            .map(|(file, result)| (file.to_string(), result.clone()))
+            .collect();
+        let unlocked = unlocked.iter().map(|file| file.to_string()).collect();
+        campaign().progress(&results, &unlocked)
+    }
+
+    fn unlocked(progress: &CampaignProgress) -> Vec<String> {
+        progress
+            .unlocked_files(&campaign())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn start_of_the_campaign() {
+        let progress = progress(&[], &[]);
+        assert_eq!(progress.stars, 0);
+        let worlds = progress
+            .worlds
+            .iter()
+            .map(|w| w.unlocked)
+            .collect::<Vec<_>>();
+        assert_eq!(worlds, [true, false, false]);
+        // Levels that are always unlocked still need their world to be unlocked.
+        assert_eq!(unlocked(&progress), ["a1"]);
+    }
+
+    #[test]
+    fn previous_level_unlocks_the_next() {
+        let won = progress(&[("a1", result(true, 1))], &[]);
+        assert_eq!(unlocked(&won), ["a1", "a2"]);
+        // A lost level doesn't count.
+        let lost = progress(&[("a1", result(false, 0))], &[]);
+        assert_eq!(unlocked(&lost), ["a1"]);
+    }
+
+    #[test]
+    fn stars_unlock_levels_and_worlds() {
+        let four = progress(&[("a1", result(true, 3)), ("a2", result(true, 1))], &[]);
+        assert_eq!(four.stars, 4);
+        assert_eq!(four.worlds[0].stars, 4);
+        // Four stars open world C, but not level a3, so world A isn't completed.
+        assert_eq!(unlocked(&four), ["a1", "a2", "c1"]);
+        assert!(!four.worlds[0].is_completed());
+
+        let five = progress(&[("a1", result(true, 3)), ("a2", result(true, 2))], &[]);
+        assert_eq!(unlocked(&five), ["a1", "a2", "a3", "c1"]);
+    }
+
+    #[test]
+    fn completed_world_unlocks_the_next() {
+        let results = [
+            ("a1", result(true, 3)),
+            ("a2", result(true, 2)),
+            ("a3", result(true, 0)),
+        ];
+        let progress = progress(&results, &[]);
+        assert!(progress.worlds[0].is_completed());
+        assert_eq!(progress.worlds[0].levels_won, 3);
+        assert_eq!(unlocked(&progress), ["a1", "a2", "a3", "b1", "b2", "c1"]);
+    }
+
+    #[test]
+    fn unlocked_levels_stay_unlocked() {
+        // E.g. levels that were unlocked before they were moved to a later world.
+        let progress = progress(&[], &["a3", "b1"]);
+        assert!(progress.worlds[1].unlocked);
+        assert!(!progress.worlds[2].unlocked);
+        assert_eq!(unlocked(&progress), ["a1", "a3", "b1", "b2"]);
+    }
+}
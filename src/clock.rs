@@ -0,0 +1,129 @@
+/// The length in seconds of one step of game time.
+pub const TIME_STEP: f64 = 1.0 / 60.0;
+
+/// Real time of a frame beyond this is dropped, so that a frame that took long,
+/// e.g. while the window was dragged, doesn't skip a large part of the level.
+const MAX_FRAME_TIME: f64 = 0.25;
+
+/// Game time, which passes in steps of [`TIME_STEP`] regardless of the frame rate.
+///
+/// Every frame adds its real time with [`GameClock::tick`], which says how many steps to take,
+/// so the same game time always takes the same steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameClock {
+    /// No time passes while the clock is paused.
+    pub paused: bool,
+    /// Game time per second of real time.
+    speed: f64,
+    /// Game time in seconds that hasn't made a full step yet.
+    accumulator: f64,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+            accumulator: 0.0,
+        }
+    }
+}
+
+impl GameClock {
+    /// The speeds to choose from: slow motion, normal speed and fast forward.
+    pub const SPEEDS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the game time per second of real time, which can't be negative.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Adds a frame that took `real_seconds`, and returns how many steps of game time it makes.
+    /// The part of a step that is left over is kept for the next frame.
+    pub fn tick(&mut self, real_seconds: f64) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        self.accumulator += real_seconds.clamp(0.0, MAX_FRAME_TIME) * self.speed;
+        let steps = (self.accumulator / TIME_STEP).floor();
+        self.accumulator -= steps * TIME_STEP;
+        steps as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The game time of `frames` frames of `frame_time` seconds.
+    fn game_time(clock: &mut GameClock, frames: usize, frame_time: f64) -> f64 {
+        (0..frames).map(|_| clock.tick(frame_time)).sum::<u32>() as f64 * TIME_STEP
+    }
+
+    #[test]
+    fn steps_dont_depend_on_the_frame_rate() {
+        for fps in [30.0, 60.0, 144.0, 7.0] {
+            let mut clock = GameClock::default();
+            let time = game_time(&mut clock, (fps * 10.0) as usize, 1.0 / fps);
+            // At most the step that is still in the accumulator is missing.
+            assert!(
+                (time - 10.0).abs() < TIME_STEP + 1e-9,
+                "{} fps: {}",
+                fps,
+                time
+            );
+        }
+    }
+
+    #[test]
+    fn remainder_is_kept() {
+        let mut clock = GameClock::default();
+        // Two thirds of a step make no step, the next two thirds make one, with a third left.
+        assert_eq!(clock.tick(TIME_STEP * 2.0 / 3.0), 0);
+        assert_eq!(clock.tick(TIME_STEP * 2.0 / 3.0), 1);
+        assert_eq!(clock.tick(TIME_STEP * 2.0 / 3.0), 1);
+        assert_eq!(clock.tick(TIME_STEP * 2.5), 2);
+        assert_eq!(clock.tick(TIME_STEP * 0.5), 1);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut clock = GameClock::default();
+        assert_eq!(clock.tick(5.0), (MAX_FRAME_TIME / TIME_STEP).round() as u32);
+        assert_eq!(clock.tick(-1.0), 0);
+    }
+
+    #[test]
+    fn paused_clock_stands_still() {
+        let mut clock = GameClock::default();
+        clock.tick(TIME_STEP / 2.0);
+        clock.paused = true;
+        assert_eq!(clock.tick(1.0), 0);
+        clock.paused = false;
+        // The half step from before the pause is still there.
+        assert_eq!(clock.tick(TIME_STEP / 2.0), 1);
+    }
+
+    #[test]
+    fn speeds() {
+        for speed in GameClock::SPEEDS {
+            let mut clock = GameClock::default();
+            clock.set_speed(speed);
+            let time = game_time(&mut clock, 600, 1.0 / 60.0);
+            assert!(
+                (time - 10.0 * speed).abs() < TIME_STEP + 1e-9,
+                "{}×: {}",
+                speed,
+                time
+            );
+        }
+        let mut clock = GameClock::default();
+        clock.set_speed(-2.0);
+        assert_eq!(clock.speed(), 0.0);
+        assert_eq!(clock.tick(1.0), 0);
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::math::Matrix;
+use crate::trajectory::Trajectory;
+
+/// Number of points at which a curve is evaluated to find the area it covers.
+const BOUNDS_SAMPLES: usize = 500;
+
+/// How the components of a [`Curve`] make up a path in the plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CurveKind {
+    /// The graph of a function `y(x)`.
+    #[default]
+    Graph,
+    /// A parametric curve `(x(t), y(t))`, which can loop back.
+    Parametric,
+    /// A polar curve `r(θ)`, which can spiral.
+    Polar,
+}
+
+impl CurveKind {
+    pub const ALL: [CurveKind; 3] = [CurveKind::Graph, CurveKind::Parametric, CurveKind::Polar];
+
+    /// The names of the components, in the order of [`Curve::components`].
+    pub fn component_names(self) -> &'static [&'static str] {
+        match self {
+            CurveKind::Graph => &["y"],
+            CurveKind::Parametric => &["x", "y"],
+            CurveKind::Polar => &["r"],
+        }
+    }
+
+    /// The name of the parameter along which the curve is revealed.
+    pub fn variable(self) -> &'static str {
+        match self {
+            CurveKind::Graph => "x",
+            CurveKind::Parametric => "t",
+            CurveKind::Polar => "θ",
+        }
+    }
+
+    /// Writes the formula of a component, e.g. `y(x) = ax + b`.
+    ///
+    /// The formula has to be written with the [`CurveKind::variable`] of the curve.
+    pub fn component_formula(self, i: usize, formula: &str) -> String {
+        format!(
+            "{}({}) = {}",
+            self.component_names()[i],
+            self.variable(),
+            formula
+        )
+    }
+}
+
+impl fmt::Display for CurveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CurveKind::Graph => "Graph",
+            CurveKind::Parametric => "Parametric",
+            CurveKind::Polar => "Polar",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A path in the plane, made of one or two [`Trajectory`] components.
+///
+/// The path goes from the first to the second root of the main component,
+/// i.e. from launch to landing for graphs and parametric curves,
+/// and from the origin back to the origin for polar curves.
+#[derive(Clone)]
+pub struct Curve {
+    kind: CurveKind,
+    components: Vec<Box<dyn Trajectory>>,
+}
+
+impl Curve {
+    pub fn graph(y: Box<dyn Trajectory>) -> Self {
+        Self {
+            kind: CurveKind::Graph,
+            components: vec![y],
+        }
+    }
+
+    pub fn parametric(x: Box<dyn Trajectory>, y: Box<dyn Trajectory>) -> Self {
+        Self {
+            kind: CurveKind::Parametric,
+            components: vec![x, y],
+        }
+    }
+
+    pub fn polar(r: Box<dyn Trajectory>) -> Self {
+        Self {
+            kind: CurveKind::Polar,
+            components: vec![r],
+        }
+    }
+
+    pub fn kind(&self) -> CurveKind {
+        self.kind
+    }
+
+    pub fn components(&self) -> &[Box<dyn Trajectory>] {
+        &self.components
+    }
+
+    pub fn components_mut(&mut self) -> &mut [Box<dyn Trajectory>] {
+        &mut self.components
+    }
+
+    /// The component whose roots are the start and the end of the path: `y` or `r`.
+    pub fn main_component(&self) -> &dyn Trajectory {
+        self.components.last().unwrap().as_ref()
+    }
+
+    /// The point of the curve at parameter `t`.
+    pub fn point(&self, t: f64) -> [f64; 2] {
+        match self.kind {
+            CurveKind::Graph => [t, self.components[0].eval(t)],
+            CurveKind::Parametric => [self.components[0].eval(t), self.components[1].eval(t)],
+            CurveKind::Polar => {
+                let r = self.components[0].eval(t);
+                let (sin, cos) = t.sin_cos();
+                [r * cos, r * sin]
+            }
+        }
+    }
+
+    /// Whether the other curve has the same kind and the same families of components,
+    /// so the parameters of one can be used for the other.
+    pub fn same_shape(&self, other: &Curve) -> bool {
+        self.kind == other.kind
+            && self
+                .components
+                .iter()
+                .zip(&other.components)
+                .all(|(a, b)| a.family() == b.family() && a.params().len() == b.params().len())
+    }
+
+    /// The parameters of all components, one after the other.
+    pub fn params(&self) -> Vec<f64> {
+        self.components
+            .iter()
+            .flat_map(|component| component.params().to_vec())
+            .collect()
+    }
+
+    /// Sets the parameters of all components, in the order of [`Curve::params`].
+    /// Extra parameters are ignored, and missing ones are left as they are.
+    pub fn set_params(&mut self, params: &[f64]) {
+        let mut params = params.iter();
+        for component in &mut self.components {
+            for (param, value) in component.params_mut().iter_mut().zip(&mut params) {
+                *param = *value;
+            }
+        }
+    }
+
+    /// The names of [`Curve::params`], with the name of the component if there is more than one.
+    pub fn param_names(&self) -> Vec<String> {
+        let component_names = self.kind.component_names();
+        self.components
+            .iter()
+            .zip(component_names)
+            .flat_map(|(component, component_name)| {
+                component.param_names().into_iter().map(move |name| {
+                    if component_names.len() > 1 {
+                        format!("{} of {}", name, component_name)
+                    } else {
+                        name
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// The ranges of [`Curve::params`].
+    pub fn param_ranges(&self) -> Vec<RangeInclusive<f64>> {
+        self.components
+            .iter()
+            .flat_map(|component| {
+                (0..component.params().len()).map(move |i| component.param_range(i))
+            })
+            .collect()
+    }
+
+    pub fn reset(&mut self) {
+        for component in &mut self.components {
+            component.reset();
+        }
+    }
+
+    /// How much the points of the curve at the parameters `ts` change with each of [`Curve::params`].
+    ///
+    /// For graphs there is one row per point, as only `y` depends on the parameters.
+    /// Otherwise there are two rows per point, for `x` and `y`.
+    pub fn jacobian(&self, ts: &[f64]) -> Matrix {
+        match self.kind {
+            CurveKind::Graph => self.components[0].jacobian(ts),
+            CurveKind::Parametric => {
+                let x = self.components[0].jacobian(ts);
+                let y = self.components[1].jacobian(ts);
+                let mut matrix = Matrix::zeros(2 * ts.len(), x.cols() + y.cols());
+                for i in 0..ts.len() {
+                    for j in 0..x.cols() {
+                        matrix[(2 * i, j)] = x[(i, j)];
+                    }
+                    for j in 0..y.cols() {
+                        matrix[(2 * i + 1, x.cols() + j)] = y[(i, j)];
+                    }
+                }
+                matrix
+            }
+            CurveKind::Polar => {
+                let r = self.components[0].jacobian(ts);
+                let mut matrix = Matrix::zeros(2 * ts.len(), r.cols());
+                for (i, t) in ts.iter().enumerate() {
+                    let (sin, cos) = t.sin_cos();
+                    for j in 0..r.cols() {
+                        matrix[(2 * i, j)] = r[(i, j)] * cos;
+                        matrix[(2 * i + 1, j)] = r[(i, j)] * sin;
+                    }
+                }
+                matrix
+            }
+        }
+    }
+
+    /// The smallest and largest point of the curve for parameters in `[start, end]`.
+    pub fn bounds(&self, start: f64, end: f64) -> [[f64; 2]; 2] {
+        if self.kind == CurveKind::Graph {
+            let [min, max] = self.components[0].min_max(start, end);
+            return [[start, min], [end, max]];
+        }
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for i in 0..=BOUNDS_SAMPLES {
+            let point = self.point(start + (end - start) * i as f64 / BOUNDS_SAMPLES as f64);
+            for axis in 0..2 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        [min, max]
+    }
+
+    /// The formula of each component, with the names of the parameters.
+    pub fn templates(&self) -> Vec<String> {
+        self.components
+            .iter()
+            .enumerate()
+            .map(|(i, component)| {
+                let template = component.template(self.kind.variable());
+                self.kind.component_formula(i, &template)
+            })
+            .collect()
+    }
+
+    /// The formula of each component, with the values of the parameters.
+    pub fn formulas(&self, precision: usize) -> Vec<String> {
+        self.components
+            .iter()
+            .enumerate()
+            .map(|(i, component)| {
+                let formula = component.formula(precision, self.kind.variable());
+                self.kind.component_formula(i, &formula)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::Polynomial;
+    use crate::trajectory::{Projectile, Sinusoid};
+
+    #[test]
+    fn formulas_use_the_variable_of_the_curve() {
+        let graph = Curve::graph(Box::new(Polynomial::new([2.0, -1.0, 0.0])));
+        assert_eq!(graph.templates(), ["y(x) = ax^2 + bx + c"]);
+        assert_eq!(graph.formulas(1), ["y(x) = 2.0x^2 - x"]);
+
+        let polar = Curve::polar(Box::new(Sinusoid([1.0, 2.0, 0.0, 1.5])));
+        assert_eq!(
+            polar.templates(),
+            ["r(θ) = amplitude * sin(frequency * θ + phase) + offset"]
+        );
+        assert_eq!(polar.formulas(1), ["r(θ) = 1.0sin(2.0θ + 0.0) + 1.5"]);
+    }
+
+    #[test]
+    fn words_are_kept() {
+        let parametric = Curve::parametric(
+            Box::new(Polynomial::new([1.0, 0.0])),
+            Box::new(Projectile([45.0, 5.0, 0.0])),
+        );
+        assert_eq!(
+            parametric.templates(),
+            [
+                "x(t) = at + b",
+                "y(t) = launched from x = launch, at angle degrees with speed"
+            ]
+        );
+        assert_eq!(
+            parametric.formulas(0),
+            [
+                "x(t) = t",
+                "y(t) = launched from x = 0 at 45 degrees with speed 5"
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use crate::generator::{generate_level, Difficulty, GeneratorSettings, Rng};
+use crate::level::Level;
+use crate::score::{star_text, LevelResult};
+
+/// Mixed into the seed, so the daily levels differ from the random levels of other modes.
+const DAILY_SEED: u64 = 0x4571_7561_7461_0001;
+
+/// The lowest and highest degree of a daily challenge.
+const DEGREES: (i64, i64) = (2, 4);
+
+/// A day of the calendar, in UTC so that it is the same for every player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    /// Days since 1970-01-01.
+    days: i64,
+}
+
+impl Date {
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        Self { days }
+    }
+
+    /// The current day, in UTC.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn today() -> Self {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Self::from_days_since_epoch((seconds / 86_400) as i64)
+    }
+
+    /// The current day, in UTC.
+    /// `SystemTime` isn't available on the web, so it asks the browser.
+    #[cfg(target_arch = "wasm32")]
+    pub fn today() -> Self {
+        let millis = js_sys::Date::now();
+        Self::from_days_since_epoch((millis / 86_400_000.0).floor() as i64)
+    }
+
+    /// The year, month and day, see <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    pub fn year_month_day(self) -> (i64, u32, u32) {
+        let z = self.days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        // Months are counted from March, so the leap day is at the end of the year.
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+        (year, month as u32, day as u32)
+    }
+
+    /// The seed of the level of this day.
+    pub fn seed(self) -> u64 {
+        // One step of the generator spreads the bits of consecutive days.
+        Rng::new(DAILY_SEED ^ self.days as u64).next_u64()
+    }
+
+    /// The daily challenge of this day, which is the same on every platform.
+    pub fn daily_level(self) -> Level {
+        let mut rng = Rng::new(self.seed());
+        let degree = rng.range_int(DEGREES.0, DEGREES.1) as usize;
+        generate_level(
+            &GeneratorSettings::new(degree, Difficulty::Medium),
+            &mut rng,
+        )
+    }
+}
+
+impl fmt::Display for Date {
+    /// Formats the date like `2021-08-24`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.year_month_day();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// A summary of the result to share with other players, without giving the answer away.
+pub fn share_text(date: Date, result: &LevelResult) -> String {
+    let outcome = if result.won {
+        format!("{} {} points", star_text(result.stars), result.score)
+    } else {
+        "Missed".to_string()
+    };
+    format!(
+        "Equata Daily {}: {}\nTime left: {:.1}s, wrong guesses: {}, hints: {}",
+        date, outcome, result.time_left, result.wrong_confirms, result.hints_used
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        let date = |days| Date::from_days_since_epoch(days).to_string();
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(11_017), "2000-03-01");
+        assert_eq!(date(-1), "1969-12-31");
+        // 1900 isn't a leap year.
+        assert_eq!(date(-25_509), "1900-02-28");
+        assert_eq!(date(-25_508), "1900-03-01");
+        assert_eq!(date(18_863), "2021-08-24");
+    }
+
+    #[test]
+    fn daily_level_is_pinned() {
+        // Every platform must get this level on this day, so it may only change on purpose.
+        let date = Date::from_days_since_epoch(18_863);
+        let level = date.daily_level();
+        assert_eq!(level.enemy_path.params(), [1.0, 2.0, -3.0, 0.0]);
+        assert_eq!(level.max_time, 105.0);
+        let other_day = Date::from_days_since_epoch(18_864).daily_level();
+        assert_ne!(other_day.enemy_path.params(), level.enemy_path.params());
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::curve::CurveKind;
+use crate::level::{Level, WIN_TOLERANCE};
+use crate::math::Qr;
+
+/// Fraction of the path that is looked at to decide how ambiguous the start of a level is.
+pub const EARLY_FRACTION: f64 = 0.5;
+
+/// How far (vertically) a prediction can be from the path before the player can see the difference.
+const VISIBLE_TOLERANCE: f64 = 0.05;
+
+/// Width of the range of coefficients that is accepted by the default win condition.
+const COEFFICIENT_STEP: f64 = 2.0 * WIN_TOLERANCE;
+
+/// Number of points on the early part of the path used to estimate the ambiguity,
+/// unless the path has more parameters than that.
+const SAMPLES: usize = 50;
+
+/// An estimate of how hard a level is, with the factors that make up the score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyEstimate {
+    /// Degree of the enemy path, or the number of parameters minus one for paths that aren't graphs of polynomials.
+    pub degree: usize,
+    /// The average absolute value of the parameters.
+    pub coefficient_magnitude: f64,
+    /// Percentage of the path that is revealed per second.
+    pub reveal_rate: f64,
+    /// The smallest distance between two distinct real roots of the main component of the enemy path.
+    pub min_root_gap: f64,
+    /// Base 10 logarithm of the number of paths that fit the first
+    /// [`EARLY_FRACTION`] of the path, up to what the player can see.
+    pub ambiguity: f64,
+    /// The combined score, higher is harder.
+    pub score: f64,
+}
+
+impl DifficultyEstimate {
+    /// Scores how hard it is to solve the level.
+    pub fn new(level: &Level) -> Self {
+        let path = &level.enemy_path;
+        let params = match (path.kind(), path.main_component().as_polynomial()) {
+            (CurveKind::Graph, Some(poly)) => poly.normalized().coefs().to_vec(),
+            _ => path.params(),
+        };
+        let degree = params.len() - 1;
+        let coefficient_magnitude =
+            params.iter().map(|c| c.abs()).sum::<f64>() / params.len() as f64;
+        let reveal_rate = 100.0 / level.max_time;
+        let min_root_gap = path
+            .main_component()
+            .roots()
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .fold(f64::INFINITY, f64::min);
+        let ambiguity = ambiguity(level, EARLY_FRACTION);
+        let mut estimate = Self {
+            degree,
+            coefficient_magnitude,
+            reveal_rate,
+            min_root_gap,
+            ambiguity,
+            score: 0.0,
+        };
+        estimate.score = estimate.score_without_time() + estimate.time_score();
+        estimate
+    }
+
+    /// The part of the score that comes from the time limit.
+    /// Every parameter has to be found while the path is being revealed.
+    /// Keep in sync with [`DifficultyEstimate::calibrate_max_time`].
+    fn time_score(&self) -> f64 {
+        self.reveal_rate * (self.degree + 1) as f64 / 2.0
+    }
+
+    fn score_without_time(&self) -> f64 {
+        let root_closeness = (1.0 / self.min_root_gap).ln().max(0.0);
+        self.degree as f64
+            + 0.5 * (1.0 + self.coefficient_magnitude).log2()
+            + root_closeness
+            + 0.5 * self.ambiguity
+    }
+
+    /// The time limit that would give the level the `target` score,
+    /// or `None` if the level is harder than that even with unlimited time.
+    pub fn calibrate_max_time(&self, target: f64) -> Option<f64> {
+        let time_score = target - self.score_without_time();
+        if time_score <= 0.0 {
+            return None;
+        }
+        // Solve `time_score = 100 / max_time * (degree + 1) / 2` for the time limit.
+        Some(50.0 * (self.degree + 1) as f64 / time_score)
+    }
+}
+
+/// Estimates (base 10 logarithm of) the number of paths of the same family
+/// that match the first `fraction` of the enemy path, up to [`VISIBLE_TOLERANCE`].
+///
+/// The parameters that fit the samples form an ellipsoid (for paths that aren't polynomials, approximately),
+/// whose volume is found with the QR decomposition of the Jacobian of the path. Dividing by the size of the
+/// range accepted by the default win condition gives the number of different answers.
+pub fn ambiguity(level: &Level, fraction: f64) -> f64 {
+    let len = level.enemy_path.params().len();
+    let (start, end) = (level.start(), level.end());
+    // The QR decomposition needs at least as many samples as parameters.
+    let samples = SAMPLES.max(len);
+    let ts = (0..samples)
+        .map(|i| start + (end - start) * fraction * i as f64 / (samples - 1) as f64)
+        .collect::<Vec<_>>();
+    let qr = Qr::new(&level.enemy_path.jacobian(&ts));
+    let radius = VISIBLE_TOLERANCE * (samples as f64).sqrt();
+    let log10_count = ball_volume(len).log10() + len as f64 * (radius / COEFFICIENT_STEP).log10()
+        - qr.log10_volume();
+    // There are only so many values that the controls allow.
+    let log10_max = level
+        .enemy_path
+        .param_ranges()
+        .iter()
+        .map(|range| ((range.end() - range.start()) / COEFFICIENT_STEP).log10())
+        .sum::<f64>();
+    log10_count.clamp(0.0, log10_max)
+}
+
+/// The volume of the unit ball in `n` dimensions.
+fn ball_volume(n: usize) -> f64 {
+    match n {
+        0 => 1.0,
+        1 => 2.0,
+        n => 2.0 * PI / n as f64 * ball_volume(n - 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::Curve;
+    use crate::polynomial::Polynomial;
+
+    #[test]
+    fn more_parameters_than_samples() {
+        // x^60 - 1
+        let mut coefs = vec![0.0; 61];
+        coefs[0] = 1.0;
+        coefs[60] = -1.0;
+        let path = Curve::graph(Box::new(Polynomial::new(coefs)));
+        let level = Level::new(path, 100.0).unwrap();
+        let estimate = DifficultyEstimate::new(&level);
+        assert_eq!(estimate.degree, 60);
+        assert!(estimate.ambiguity.is_finite());
+        assert!(estimate.score.is_finite());
+    }
+}
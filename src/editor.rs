@@ -0,0 +1,576 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{
+        self,
+        plot::{Line, Points, Polygon, Value, Values},
+        Color32,
+    },
+    EguiContext,
+};
+use std::f64::consts::PI;
+
+use equata::curve::CurveKind;
+use equata::hint::{Hint, HintKind};
+use equata::level::{Level, DEFAULT_SNAP_FIT_COST};
+use equata::level_file::{ComponentDefinition, LevelDefinition};
+use equata::polynomial::Polynomial;
+use equata::score::StarThresholds;
+use equata::share_code;
+use equata::trajectory::TrajectoryFamily;
+use equata::win_condition::{WinCondition, WinCriterion};
+
+use crate::{
+    difficulty_breakdown, level_plot, solvability_summary, AppState, CurrentLevelFile, GameMode,
+    LevelFiles, LevelReport,
+};
+
+/// The highest degree of enemy path that can be made in the editor.
+const MAX_DEGREE: usize = 8;
+
+/// The level that is being made in the level editor.
+pub struct LevelEditor {
+    definition: LevelDefinition,
+    /// Name of the file in `assets/levels/`, without the extension.
+    file_name: String,
+    /// The level file that was last saved, so play testing can hot-reload it.
+    saved_file: Option<HandleUntyped>,
+    /// Outcome of the last attempt to save the level.
+    save_result: Option<Result<String, String>>,
+    /// The last share code of the level, which was also copied to the clipboard.
+    share_code: Option<String>,
+    /// The difficulty score that the time limit is calibrated to.
+    target_score: f64,
+}
+
+impl Default for LevelEditor {
+    fn default() -> Self {
+        Self {
+            definition: LevelDefinition {
+                pack: "Custom".to_string(),
+                title: "New Level".to_string(),
+                difficulty: "Medium".to_string(),
+                curve: CurveKind::Graph,
+                family: TrajectoryFamily::Polynomial,
+                coefficients: vec![-1.0, 0.0, 1.0],
+                x_component: None,
+                max_time: 60.0,
+                hints: Vec::new(),
+                win_condition: WinCondition::default(),
+                snap_fit_cost: DEFAULT_SNAP_FIT_COST,
+                stars: StarThresholds::default(),
+            },
+            file_name: "new_level".to_string(),
+            saved_file: None,
+            save_result: None,
+            share_code: None,
+            target_score: 6.0,
+        }
+    }
+}
+
+/// Writes the level to `assets/levels/<file_name>.level.ron`.
+/// Returns the path of the file relative to the assets folder.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_level_file(definition: &LevelDefinition, file_name: &str) -> Result<String, String> {
+    if file_name.is_empty()
+        || !file_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err("The file name can only contain letters, digits, '_' and '-'.".to_string());
+    }
+    let asset_path = format!("levels/{}.level.ron", file_name);
+    let path = bevy::asset::FileAssetIo::get_root_path()
+        .join("assets")
+        .join(&asset_path);
+    let contents = definition.to_ron().map_err(|err| err.to_string())?;
+    std::fs::write(&path, contents).map_err(|err| err.to_string())?;
+    Ok(asset_path)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_level_file(_definition: &LevelDefinition, _file_name: &str) -> Result<String, String> {
+    Err("Levels can't be saved in the web version.".to_string())
+}
+
+/// The list of hints of the level, where hints can be added, changed and removed.
+/// `param_count` is the number of parameters of the enemy path, which hints can reveal.
+fn ui_hints(ui: &mut egui::Ui, hints: &mut Vec<Hint>, param_count: usize) {
+    ui.label("Hints");
+    let mut removed = None;
+    for (i, hint) in hints.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(("Hint Kind", i))
+                .selected_text(hint.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in HintKind::examples() {
+                        if ui
+                            .selectable_label(hint.kind.same_kind(&kind), kind.to_string())
+                            .clicked()
+                            && !hint.kind.same_kind(&kind)
+                        {
+                            *hint = Hint::new(kind);
+                        }
+                    }
+                });
+            match &mut hint.kind {
+                HintKind::Text(text) => {
+                    ui.text_edit_singleline(text);
+                }
+                HintKind::RevealCoefficient(k) => {
+                    ui.add(
+                        egui::DragValue::new(k)
+                            .clamp_range(0..=param_count.saturating_sub(1))
+                            .prefix("parameter "),
+                    );
+                }
+                HintKind::ShowRoots => {}
+                HintKind::RevealPath(fraction) => {
+                    let mut percentage = *fraction * 100.0;
+                    ui.add(
+                        egui::DragValue::new(&mut percentage)
+                            .clamp_range(1.0..=100.0)
+                            .suffix("%"),
+                    );
+                    *fraction = percentage / 100.0;
+                }
+            }
+            ui.add(
+                egui::DragValue::new(&mut hint.cost)
+                    .clamp_range(0.0..=600.0)
+                    .speed(0.5)
+                    .prefix("cost: ")
+                    .suffix("s"),
+            );
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        hints.remove(i);
+    }
+    if ui.button("Add hint").clicked() {
+        hints.push(Hint::new(HintKind::Text(String::new())));
+    }
+}
+
+/// The controls for the component at position `index` of the enemy path.
+fn ui_component(
+    ui: &mut egui::Ui,
+    kind: CurveKind,
+    index: usize,
+    family: &mut TrajectoryFamily,
+    coefficients: &mut Vec<f64>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Family");
+        egui::ComboBox::from_id_source(("Path Family", index))
+            .selected_text(family.to_string())
+            .show_ui(ui, |ui| {
+                for new_family in TrajectoryFamily::ALL {
+                    if ui
+                        .selectable_label(*family == new_family, new_family.to_string())
+                        .clicked()
+                        && *family != new_family
+                    {
+                        *family = new_family;
+                        *coefficients = new_family.example();
+                    }
+                }
+            });
+        if *family == TrajectoryFamily::Polynomial {
+            ui.label("Degree");
+            let mut degree = coefficients.len() - 1;
+            if ui
+                .add(egui::DragValue::new(&mut degree).clamp_range(1..=MAX_DEGREE))
+                .changed()
+            {
+                // Keep the lower degree terms, as those are the ones that stay the same.
+                let mut poly = Polynomial::new(coefficients.iter().copied());
+                poly.pad_to(degree + 1);
+                let extra = poly.len() - (degree + 1);
+                *coefficients = poly.coefs()[extra..].to_vec();
+            }
+        }
+    });
+
+    let count = coefficients.len();
+    ui.label(format!(
+        "Path: {}",
+        kind.component_formula(index, &family.template(count, kind.variable()))
+    ));
+    let names = family.param_names(count);
+    for (i, (param, name)) in coefficients.iter_mut().zip(names).enumerate() {
+        // Same range as the controls in game, otherwise the level can't be solved.
+        ui.add(
+            egui::DragValue::new(param)
+                .clamp_range(family.param_range(i))
+                .speed(0.1)
+                .prefix(format!("{}: ", name)),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn ui_level_editor(
+    egui_ctx: ResMut<EguiContext>,
+    mut editor: ResMut<LevelEditor>,
+    mut app_state: ResMut<State<AppState>>,
+    mut level: ResMut<Level>,
+    mut game_mode: ResMut<GameMode>,
+    mut current_level_file: ResMut<CurrentLevelFile>,
+    mut level_files: ResMut<LevelFiles>,
+    asset_server: Res<AssetServer>,
+) {
+    let ctx = egui_ctx.ctx();
+    let editor = &mut *editor;
+    egui::SidePanel::left("Editor Panel").show(ctx, |ui| {
+        ui.heading("Level Editor");
+        ui.separator();
+        egui::Grid::new("Editor Grid").show(ui, |ui| {
+            ui.label("File name");
+            ui.text_edit_singleline(&mut editor.file_name);
+            ui.end_row();
+            ui.label("Pack");
+            ui.text_edit_singleline(&mut editor.definition.pack);
+            ui.end_row();
+            ui.label("Title");
+            ui.text_edit_singleline(&mut editor.definition.title);
+            ui.end_row();
+            ui.label("Difficulty");
+            ui.text_edit_singleline(&mut editor.definition.difficulty);
+            ui.end_row();
+            ui.label("Time limit");
+            ui.add(
+                egui::DragValue::new(&mut editor.definition.max_time)
+                    .clamp_range(1.0..=600.0)
+                    .speed(1.0)
+                    .suffix("s"),
+            );
+            ui.end_row();
+            ui.label("Snap fit cost");
+            ui.add(
+                egui::DragValue::new(&mut editor.definition.snap_fit_cost)
+                    .clamp_range(0.0..=600.0)
+                    .speed(0.5)
+                    .suffix("s"),
+            )
+            .on_hover_text("Time added for each snap fit of the marked points.");
+            ui.end_row();
+            ui.label("Stars");
+            ui.horizontal(|ui| {
+                let stars = &mut editor.definition.stars;
+                for threshold in [&mut stars.one, &mut stars.two, &mut stars.three] {
+                    ui.add(egui::DragValue::new(threshold).speed(10.0));
+                }
+            })
+            .response
+            .on_hover_text("Scores needed for one, two and three stars.");
+            ui.end_row();
+            ui.label("Win condition");
+            let win_condition = &mut editor.definition.win_condition;
+            egui::ComboBox::from_id_source("Win Criterion")
+                .selected_text(win_condition.criterion.to_string())
+                .show_ui(ui, |ui| {
+                    for criterion in WinCriterion::ALL {
+                        if ui
+                            .selectable_label(
+                                win_condition.criterion == criterion,
+                                criterion.to_string(),
+                            )
+                            .clicked()
+                        {
+                            *win_condition = WinCondition::new(criterion);
+                        }
+                    }
+                });
+            ui.end_row();
+            ui.label("Tolerance");
+            ui.add(
+                egui::DragValue::new(&mut win_condition.tolerance)
+                    .clamp_range(0.001..=1.0)
+                    .speed(0.001),
+            );
+            ui.end_row();
+            ui.label("Curve");
+            let definition = &mut editor.definition;
+            egui::ComboBox::from_id_source("Curve Kind")
+                .selected_text(definition.curve.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in CurveKind::ALL {
+                        if ui
+                            .selectable_label(definition.curve == kind, kind.to_string())
+                            .clicked()
+                        {
+                            definition.curve = kind;
+                        }
+                    }
+                });
+            ui.end_row();
+        });
+        ui.separator();
+
+        let definition = &mut editor.definition;
+        match definition.curve {
+            CurveKind::Parametric => {
+                // Start with x(t) = t, which is the same as a graph.
+                let x = definition
+                    .x_component
+                    .get_or_insert_with(|| ComponentDefinition {
+                        family: TrajectoryFamily::Polynomial,
+                        coefficients: vec![1.0, 0.0],
+                    });
+                ui_component(
+                    ui,
+                    CurveKind::Parametric,
+                    0,
+                    &mut x.family,
+                    &mut x.coefficients,
+                );
+                ui.separator();
+                ui_component(
+                    ui,
+                    CurveKind::Parametric,
+                    1,
+                    &mut definition.family,
+                    &mut definition.coefficients,
+                );
+            }
+            kind => {
+                definition.x_component = None;
+                ui_component(
+                    ui,
+                    kind,
+                    0,
+                    &mut definition.family,
+                    &mut definition.coefficients,
+                );
+            }
+        }
+        let param_count = editor.definition.coefficients.len()
+            + editor
+                .definition
+                .x_component
+                .as_ref()
+                .map_or(0, |x| x.coefficients.len());
+        ui_hints(ui, &mut editor.definition.hints, param_count);
+        ui.separator();
+
+        if let Ok(main) = editor
+            .definition
+            .family
+            .build(&editor.definition.coefficients)
+        {
+            let roots = match main.as_polynomial() {
+                Some(poly) => poly
+                    .roots(1e-9)
+                    .iter()
+                    .map(|root| match root.multiplicity {
+                        1 => format!("{:.2}", root.x),
+                        n => format!("{:.2} (x{})", root.x, n),
+                    })
+                    .collect::<Vec<_>>(),
+                None => main.roots().iter().map(|x| format!("{:.2}", x)).collect(),
+            };
+            let kind = editor.definition.curve;
+            ui.label(format!(
+                "Roots of {}: {}",
+                kind.component_names().last().unwrap(),
+                roots.join(", ")
+            ));
+        }
+        let preview = editor.definition.to_level();
+        match &preview {
+            Ok(preview) => {
+                ui.label(format!(
+                    "Visible area: x from {:.2} to {:.2}, y from {:.2} to {:.2}",
+                    preview.limits[0].x,
+                    preview.limits[1].x,
+                    preview.limits[0].y,
+                    preview.limits[1].y,
+                ));
+                ui.colored_label(Color32::GREEN, "Valid level.");
+                let LevelReport {
+                    estimate,
+                    solvability,
+                } = LevelReport::new(preview);
+                ui.label(format!("Difficulty score: {:.1}", estimate.score))
+                    .on_hover_text(difficulty_breakdown(&estimate));
+                let summary = solvability_summary(&solvability, preview.max_time);
+                if solvability.is_fair() {
+                    ui.label(summary);
+                } else {
+                    ui.colored_label(Color32::RED, summary);
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut editor.target_score)
+                            .clamp_range(0.0..=30.0)
+                            .speed(0.1)
+                            .prefix("Target score: "),
+                    );
+                    let calibrated = estimate.calibrate_max_time(editor.target_score);
+                    let hover_text = match calibrated {
+                        Some(max_time) => format!("Set the time limit to {:.0}s.", max_time),
+                        None => {
+                            "The level is harder than this even without a time limit.".to_string()
+                        }
+                    };
+                    if ui
+                        .add(
+                            egui::Button::new("Calibrate time limit").enabled(calibrated.is_some()),
+                        )
+                        .on_hover_text(hover_text)
+                        .clicked()
+                    {
+                        if let Some(max_time) = calibrated {
+                            editor.definition.max_time = max_time.round().clamp(1.0, 600.0);
+                        }
+                    }
+                });
+            }
+            Err(err) => {
+                ui.colored_label(Color32::RED, err);
+            }
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(egui::Button::new("Save").enabled(preview.is_ok()))
+                .on_hover_text(format!(
+                    "Save the level to 'assets/levels/{}.level.ron'.",
+                    editor.file_name
+                ))
+                .clicked()
+            {
+                let result = save_level_file(&editor.definition, &editor.file_name);
+                if let Ok(asset_path) = &result {
+                    let handle = asset_server.load_untyped(asset_path.as_str());
+                    if !level_files.handles.contains(&handle) {
+                        level_files.handles.push(handle.clone());
+                    }
+                    editor.saved_file = Some(handle);
+                }
+                editor.save_result = Some(result.map(|path| format!("Saved to '{}'.", path)));
+            }
+            if ui
+                .add(egui::Button::new("Share Code").enabled(preview.is_ok()))
+                .on_hover_text(
+                    "Copy a code of the level, which others can enter in the level menu.",
+                )
+                .on_hover_text("Hints and stars aren't part of the code.")
+                .clicked()
+            {
+                let code = share_code::encode(&editor.definition);
+                ui.output().copied_text = code.clone();
+                editor.share_code = Some(code);
+            }
+            if ui
+                .add(egui::Button::new("Play Test").enabled(preview.is_ok()))
+                .on_hover_text("Play the level as it is now.")
+                .clicked()
+            {
+                if let Ok(preview) = preview {
+                    *level = preview;
+                    current_level_file.0 = editor.saved_file.as_ref().map(|handle| handle.id);
+                    *game_mode = GameMode::PlayTest;
+                    let _ = app_state.set(AppState::InGame);
+                }
+            }
+            if ui.button("Main Menu").clicked() {
+                let _ = app_state.set(AppState::MainMenu);
+            }
+        });
+        if let Some(code) = &editor.share_code {
+            ui.label("Copied the share code:");
+            ui.add(egui::Label::new(code).monospace());
+        }
+        match &editor.save_result {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+            }
+            None => {}
+        }
+    });
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let curve = editor.definition.curve().ok().map(|enemy_path| {
+            let roots = Points::new(Values::from_values_iter(
+                enemy_path.main_component().roots().into_iter().map(|t| {
+                    let [x, y] = enemy_path.point(t);
+                    Value::new(x, y)
+                }),
+            ))
+            .name("Roots")
+            .color(Color32::YELLOW)
+            .radius(5.0_f32);
+            let values = match enemy_path.kind() {
+                CurveKind::Graph => Values::from_explicit_callback(
+                    move |x| enemy_path.main_component().eval(x),
+                    ..,
+                    512,
+                ),
+                // Curves that aren't graphs can't be drawn for every x, so a range of parameters is drawn instead.
+                kind => {
+                    let range = if kind == CurveKind::Polar {
+                        -2.0 * PI..=2.0 * PI
+                    } else {
+                        -5.0..=5.0
+                    };
+                    Values::from_parametric_callback(
+                        move |t| {
+                            let [x, y] = enemy_path.point(t);
+                            (x, y)
+                        },
+                        range,
+                        1024,
+                    )
+                }
+            };
+            let curve = Line::new(values).name("Curve").color(Color32::GRAY);
+            (curve, roots)
+        });
+
+        let plot = match editor.definition.to_level() {
+            Ok(preview) => {
+                let enemy_path = Line::new(Values::from_values_iter(
+                    preview.domain_range_path(0.01).map(|t| {
+                        let [x, y] = preview.enemy_point(t);
+                        Value::new(x, y)
+                    }),
+                ))
+                .name("Enemy Path")
+                .color(Color32::RED)
+                .width(2.5_f32);
+                let [min, max] = preview.limits;
+                let limits = Polygon::new(Values::from_values(vec![
+                    Value::new(min.x, min.y),
+                    Value::new(max.x, min.y),
+                    Value::new(max.x, max.y),
+                    Value::new(min.x, max.y),
+                ]))
+                .name("Visible Area")
+                .color(Color32::LIGHT_BLUE)
+                .fill_alpha(0.05_f32);
+                level_plot("rocket_paths", &preview)
+                    .polygon(limits)
+                    .line(enemy_path)
+            }
+            // There is no visible area yet, so just show the area around the origin.
+            Err(_) => level_plot("rocket_paths", &Level::default())
+                .include_x(-5.0)
+                .include_x(5.0)
+                .include_y(-5.0)
+                .include_y(5.0),
+        };
+        match curve {
+            Some((curve, roots)) => ui.add(plot.line(curve).points(roots)),
+            None => ui.add(plot),
+        };
+    });
+}
@@ -0,0 +1,449 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::polynomial::Polynomial;
+
+/// The highest power that can be written, so typos like `x^99` don't create huge polynomials.
+const MAX_EXPONENT: u32 = 20;
+
+/// The highest degree of a polynomial while it is expanded,
+/// as nested powers like `((x^20)^20)^20` multiply their exponents.
+const MAX_DEGREE: usize = 20;
+
+/// The names that can be used for the variable, see [`crate::curve::CurveKind::variable`].
+const VARIABLES: [char; 3] = ['x', 't', 'θ'];
+
+/// A problem with an equation, with the part of the text where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// Byte range of the text that caused the error.
+    /// Empty at the end of the text if more input was expected.
+    pub span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.message, self.span.start + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a polynomial written like `2x^3 - 0.5x + 1` or `(x-1)(x+2)`, and expands it.
+///
+/// The expression can use numbers, the variable (`x`, `t` or `θ`, but only one of them),
+/// `+`, `-`, `*`, division by numbers, `^` with whole exponents, and parentheses.
+/// Multiplication can be left out, as in `2x` or `3(x + 1)`.
+pub fn parse_polynomial(input: &str) -> Result<Polynomial, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        end: input.len(),
+    };
+    let poly = parser.sum()?;
+    match parser.peek() {
+        // Adding zero turns the `-0.0` left by negations into `0.0`.
+        None => Ok(Polynomial::new(poly.coefs().iter().map(|c| c + 0.0))),
+        Some(Token {
+            kind: TokenKind::RightParen,
+            span,
+        }) => Err(ParseError::new("Unmatched ')'", span.clone())),
+        Some(token) => Err(ParseError::new(
+            "Expected an operator like '+' or '*'",
+            token.span.clone(),
+        )),
+    }
+}
+
+/// Parses a polynomial like [`parse_polynomial`], and pads it to `len` coefficients,
+/// e.g. for the controls of a polynomial of degree `len - 1`.
+pub fn parse_coefficients(input: &str, len: usize) -> Result<Vec<f64>, ParseError> {
+    let mut poly = parse_polynomial(input)?.normalized();
+    if poly.len() > len {
+        return Err(ParseError::new(
+            format!(
+                "The degree is {}, but the path has degree {}",
+                poly.degree(),
+                len.saturating_sub(1)
+            ),
+            0..input.len(),
+        ));
+    }
+    poly.pad_to(len);
+    Ok(poly.coefs().to_vec())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Number(f64),
+    Variable(char),
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut variable = None;
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let end = start + c.len_utf8();
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '0'..='9' | '.' => {
+                let mut end = end;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let number = input[start..end]
+                    .parse()
+                    .map_err(|_| ParseError::new("Invalid number", start..end))?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(number),
+                    span: start..end,
+                });
+                continue;
+            }
+            c if VARIABLES.contains(&c) => {
+                match variable {
+                    Some(other) if other != c => {
+                        return Err(ParseError::new(
+                            format!("Mixes the variables {} and {}", other, c),
+                            start..end,
+                        ))
+                    }
+                    _ => variable = Some(c),
+                }
+                TokenKind::Variable(c)
+            }
+            '+' => TokenKind::Plus,
+            '-' | '−' => TokenKind::Minus,
+            '*' | '·' | '×' => TokenKind::Times,
+            '/' => TokenKind::Divide,
+            '^' => TokenKind::Power,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            c => {
+                return Err(ParseError::new(
+                    format!("Unexpected character '{}'", c),
+                    start..end,
+                ))
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser that expands the polynomial while it parses.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    /// Length of the input, where errors about missing input point to.
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    /// `sum = product (('+' | '-') product)*`
+    fn sum(&mut self) -> Result<Polynomial, ParseError> {
+        let mut poly = self.product()?;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Plus => {
+                    self.next();
+                    poly = poly + self.product()?;
+                }
+                TokenKind::Minus => {
+                    self.next();
+                    poly = poly - self.product()?;
+                }
+                _ => break,
+            }
+        }
+        Ok(poly)
+    }
+
+    /// `product = signed (('*' | '/')? signed)*`, where a missing operator is a multiplication.
+    fn product(&mut self) -> Result<Polynomial, ParseError> {
+        let start = self.span_start();
+        let mut poly = self.signed()?;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Times => {
+                    self.next();
+                    let factor = self.signed()?;
+                    poly = self.multiply(poly, factor, start)?;
+                }
+                TokenKind::Divide => {
+                    self.next();
+                    let start = self.span_start();
+                    let divisor = self.signed()?.normalized();
+                    let span = start..self.span_end();
+                    if divisor.len() > 1 {
+                        return Err(ParseError::new("Can only divide by numbers", span));
+                    }
+                    let divisor = divisor.coefs().first().copied().unwrap_or(0.0);
+                    if divisor == 0.0 {
+                        return Err(ParseError::new("Division by zero", span));
+                    }
+                    poly = poly * (1.0 / divisor);
+                }
+                TokenKind::Number(_) | TokenKind::Variable(_) | TokenKind::LeftParen => {
+                    let factor = self.signed()?;
+                    poly = self.multiply(poly, factor, start)?;
+                }
+                _ => break,
+            }
+        }
+        Ok(poly)
+    }
+
+    /// Multiplies the product that started at `start` with the factor that was just parsed.
+    fn multiply(
+        &self,
+        poly: Polynomial,
+        factor: Polynomial,
+        start: usize,
+    ) -> Result<Polynomial, ParseError> {
+        if poly.degree() + factor.degree() > MAX_DEGREE {
+            return Err(ParseError::new(
+                format!("The degree can be at most {}", MAX_DEGREE),
+                start..self.span_end(),
+            ));
+        }
+        Ok(poly * factor)
+    }
+
+    /// `signed = ('+' | '-') signed | power`
+    fn signed(&mut self) -> Result<Polynomial, ParseError> {
+        match self.peek().map(|token| token.kind) {
+            Some(TokenKind::Plus) => {
+                self.next();
+                self.signed()
+            }
+            Some(TokenKind::Minus) => {
+                self.next();
+                Ok(-self.signed()?)
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// `power = atom ('^' number)?`, as in `-x^2 = -(x^2)`.
+    fn power(&mut self) -> Result<Polynomial, ParseError> {
+        let start = self.span_start();
+        let base = self.atom()?;
+        if self.peek().map(|token| token.kind) != Some(TokenKind::Power) {
+            return Ok(base);
+        }
+        self.next();
+        let (exponent, span) = match self.next() {
+            Some(&Token {
+                kind: TokenKind::Number(exponent),
+                ref span,
+            }) => (exponent, span.clone()),
+            Some(token) => {
+                return Err(ParseError::new(
+                    "Expected a whole number as the exponent",
+                    token.span.clone(),
+                ))
+            }
+            None => return Err(ParseError::new("Expected an exponent", self.end..self.end)),
+        };
+        if exponent.fract() != 0.0 || exponent > MAX_EXPONENT as f64 {
+            return Err(ParseError::new(
+                format!("The exponent must be a whole number up to {}", MAX_EXPONENT),
+                span,
+            ));
+        }
+        if base.degree() * exponent as usize > MAX_DEGREE {
+            return Err(ParseError::new(
+                format!("The degree can be at most {}", MAX_DEGREE),
+                start..span.end,
+            ));
+        }
+        let mut poly = Polynomial::constant(1.0);
+        for _ in 0..exponent as u32 {
+            poly = &poly * &base;
+        }
+        Ok(poly)
+    }
+
+    /// `atom = number | variable | '(' sum ')'`
+    fn atom(&mut self) -> Result<Polynomial, ParseError> {
+        let end = self.end;
+        match self.next().cloned() {
+            Some(Token {
+                kind: TokenKind::Number(value),
+                ..
+            }) => Ok(Polynomial::constant(value)),
+            Some(Token {
+                kind: TokenKind::Variable(_),
+                ..
+            }) => Ok(Polynomial::x()),
+            Some(Token {
+                kind: TokenKind::LeftParen,
+                span,
+            }) => {
+                let poly = self.sum()?;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::RightParen,
+                        ..
+                    }) => Ok(poly),
+                    _ => Err(ParseError::new("Unmatched '('", span)),
+                }
+            }
+            Some(token) => Err(ParseError::new(
+                "Expected a number, a variable or '('",
+                token.span,
+            )),
+            None => Err(ParseError::new(
+                "Expected a number, a variable or '('",
+                end..end,
+            )),
+        }
+    }
+
+    /// Where the next token starts.
+    fn span_start(&self) -> usize {
+        self.peek().map_or(self.end, |token| token.span.start)
+    }
+
+    /// Where the previous token ends.
+    fn span_end(&self) -> usize {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map_or(self.end, |token| token.span.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The coefficients of the parsed polynomial, from the highest power down.
+    fn coefs(input: &str) -> Vec<f64> {
+        parse_polynomial(input)
+            .unwrap_or_else(|err| panic!("{}: {}", input, err))
+            .normalized()
+            .coefs()
+            .to_vec()
+    }
+
+    fn error(input: &str) -> ParseError {
+        parse_polynomial(input).expect_err(input)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(coefs("1 + 2*x^2"), [2.0, 0.0, 1.0]);
+        assert_eq!(coefs("2x^2 - 3x + 1"), [2.0, -3.0, 1.0]);
+        assert_eq!(coefs("(1 + x)^2"), [1.0, 2.0, 1.0]);
+        assert_eq!(coefs("6 / 2 * x"), [3.0, 0.0]);
+        assert_eq!(coefs("1 - x - 1"), [-1.0, 0.0]);
+        assert_eq!(coefs("2^3"), [8.0]);
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!(coefs("2x"), [2.0, 0.0]);
+        assert_eq!(coefs("3(x + 1)"), [3.0, 3.0]);
+        assert_eq!(coefs("(x - 1)(x + 2)"), [1.0, 1.0, -2.0]);
+        assert_eq!(coefs("2x^2x"), [2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(coefs("0.5 t"), [0.5, 0.0]);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(coefs("-x^2"), [-1.0, 0.0, 0.0]);
+        assert_eq!(coefs("(-x)^2"), [1.0, 0.0, 0.0]);
+        assert_eq!(coefs("--x"), [1.0, 0.0]);
+        assert_eq!(coefs("x - -1"), [1.0, 1.0]);
+        assert_eq!(coefs("2 * -x"), [-2.0, 0.0]);
+        assert_eq!(coefs("−θ"), [-1.0, 0.0]);
+        // No negative zeros are left behind.
+        assert!(coefs("-(x - x)").iter().all(|c| c.is_sign_positive()));
+    }
+
+    #[test]
+    fn error_spans() {
+        assert_eq!(error("x + ").span, 4..4);
+        assert_eq!(error("2 $ x").span, 2..3);
+        assert_eq!(error("(x + 1").span, 0..1);
+        assert_eq!(error("x + 1)").span, 5..6);
+        assert_eq!(error("x / (x + 1)").span, 4..11);
+        assert_eq!(error("x / (1 - 1)").message, "Division by zero");
+        assert_eq!(error("x + t").span, 4..5);
+        assert_eq!(error("1..2").span, 0..4);
+        assert_eq!(error("x^x").span, 2..3);
+        assert_eq!(error("x^").span, 2..2);
+        assert_eq!(error("").span, 0..0);
+    }
+
+    #[test]
+    fn exponent_limit() {
+        assert_eq!(coefs("x^20").len(), 21);
+        assert_eq!(error("x^21").span, 2..4);
+        assert_eq!(error("x^1.5").span, 2..5);
+        assert!(error("2^21").message.contains("exponent"));
+    }
+
+    #[test]
+    fn degree_limit() {
+        assert_eq!(coefs("(x^2)^10").len(), 21);
+        assert_eq!(coefs("x^10 x^10").len(), 21);
+        let err = error("(((x^20)^20)^20)");
+        assert!(err.message.contains("degree"), "{}", err.message);
+        assert_eq!(err.span, 2..11);
+        assert_eq!(error("x^20 * x").span, 0..8);
+        assert_eq!(error("1 + (x^10)(x^11)").span, 4..16);
+        // Constants can still be raised to any allowed power.
+        assert_eq!(coefs("(x - x + 2)^20"), [2f64.powi(20)]);
+    }
+
+    #[test]
+    fn coefficients_are_padded() {
+        assert_eq!(parse_coefficients("x + 1", 3).unwrap(), [0.0, 1.0, 1.0]);
+        assert!(parse_coefficients("x^3", 3).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt;
+
+use crate::curve::Curve;
+use crate::level::Level;
+use crate::polynomial::Polynomial;
+
+/// How many random polynomials are tried before falling back to a simple one.
+const MAX_ATTEMPTS: usize = 500;
+
+/// Roots of generated polynomials are kept in this range,
+/// otherwise the coefficients quickly become too large.
+const ROOT_RANGE: f64 = 3.0;
+
+/// A small random number generator (SplitMix64).
+///
+/// Unlike the generators of the `rand` crate, the numbers only depend on the seed,
+/// so a seed gives the same level on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // The 53 highest bits fill the mantissa exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number in `[min, max)`.
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// A whole number in `[min, max]`.
+    pub fn range_int(&mut self, min: i64, max: i64) -> i64 {
+        min + (self.next_u64() % (max - min + 1) as u64) as i64
+    }
+
+    /// Returns true with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// Preset knobs for the generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The knobs that control how hard a generated level is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorSettings {
+    /// Degree of the enemy path, at least 2.
+    pub degree: usize,
+    /// Largest absolute value of a coefficient.
+    /// The controls in game only go up to 10, so larger values make the level impossible.
+    pub max_coefficient: f64,
+    /// Only use whole numbers for the coefficients, otherwise multiples of 0.1 are used.
+    pub integer_coefficients: bool,
+    /// Largest number of non-zero coefficients, at least 3.
+    pub max_terms: usize,
+    /// Smallest distance between the start and the end of the path.
+    pub min_width: f64,
+    /// Largest distance between the start and the end of the path.
+    pub max_width: f64,
+    /// Time in seconds to complete the level.
+    pub max_time: f64,
+}
+
+impl GeneratorSettings {
+    pub fn new(degree: usize, difficulty: Difficulty) -> Self {
+        let degree = degree.max(2);
+        match difficulty {
+            Difficulty::Easy => Self {
+                degree,
+                max_coefficient: 5.0,
+                integer_coefficients: true,
+                max_terms: degree / 2 + 2,
+                min_width: 2.0,
+                max_width: 4.0,
+                max_time: 40.0 + 30.0 * degree as f64,
+            },
+            Difficulty::Medium => Self {
+                degree,
+                max_coefficient: 8.0,
+                integer_coefficients: true,
+                max_terms: degree,
+                min_width: 1.5,
+                max_width: 3.0,
+                max_time: 30.0 + 25.0 * degree as f64,
+            },
+            Difficulty::Hard => Self {
+                degree,
+                max_coefficient: 10.0,
+                integer_coefficients: false,
+                max_terms: degree + 1,
+                min_width: 1.0,
+                max_width: 2.0,
+                max_time: 20.0 + 20.0 * degree as f64,
+            },
+        }
+    }
+
+    /// Rounds a coefficient to a whole number, or to a multiple of 0.1.
+    fn round(&self, coef: f64) -> f64 {
+        if self.integer_coefficients {
+            coef.round()
+        } else {
+            // Dividing instead of multiplying by 0.1 gives the closest float to the decimal.
+            (coef * 10.0).round() / 10.0
+        }
+    }
+
+    /// Whether the polynomial satisfies all the knobs and gives a valid level.
+    fn accepts(&self, poly: &Polynomial) -> bool {
+        let coefs = poly.coefs();
+        if coefs.len() != self.degree + 1 || coefs[0] == 0.0 {
+            return false;
+        }
+        let terms = coefs.iter().filter(|c| **c != 0.0).count();
+        if terms > self.max_terms.max(3) || coefs.iter().any(|c| c.abs() > self.max_coefficient) {
+            return false;
+        }
+        let roots = poly.roots(1e-9);
+        roots.len() >= 2 && roots[1].x - roots[0].x >= self.min_width * 0.5
+    }
+}
+
+/// Builds a random enemy path with the given settings.
+///
+/// The polynomial is built from its roots: the path goes between the first two roots,
+/// and the other factors only have roots to the right of the path, or none at all.
+/// This way [`Level::new`] always accepts the result.
+pub fn generate_poly(settings: &GeneratorSettings, rng: &mut Rng) -> Polynomial {
+    for _ in 0..MAX_ATTEMPTS {
+        let poly = random_poly(settings, rng);
+        if settings.accepts(&poly) {
+            return poly;
+        }
+    }
+    fallback_poly(settings, rng)
+}
+
+/// Builds a random level with the given settings.
+pub fn generate_level(settings: &GeneratorSettings, rng: &mut Rng) -> Level {
+    Level::new(
+        Curve::graph(Box::new(generate_poly(settings, rng))),
+        settings.max_time,
+    )
+    .expect("Generated polynomials have at least 2 roots.")
+}
+
+fn random_poly(settings: &GeneratorSettings, rng: &mut Rng) -> Polynomial {
+    let integer = settings.integer_coefficients;
+    let width = rng.range(settings.min_width, settings.max_width);
+    let (start, end) = if integer {
+        let width = width.round().max(1.0) as i64;
+        let start = rng.range_int(-ROOT_RANGE as i64, ROOT_RANGE as i64 - width);
+        (start as f64, (start + width) as f64)
+    } else {
+        let start = rng.range(-ROOT_RANGE, ROOT_RANGE - width);
+        (start, start + width)
+    };
+    let mut poly = linear_factor(start) * linear_factor(end);
+    let mut remaining = settings.degree - 2;
+    while remaining > 0 {
+        if remaining >= 2 && rng.chance(0.5) {
+            // A quadratic factor without real roots: x^2 + bx + c with b^2 < 4c.
+            let (b, c) = if integer {
+                let b = rng.range_int(-2, 2) as f64;
+                (b, (b * b / 4.0).floor() + rng.range_int(1, 2) as f64)
+            } else {
+                let b = rng.range(-2.0, 2.0);
+                (b, b * b / 4.0 + rng.range(0.2, 2.0))
+            };
+            poly = poly * Polynomial::new([1.0, b, c]);
+            remaining -= 2;
+        } else {
+            // A root to the right of the path, so it doesn't change where the path ends.
+            let root = if integer {
+                end + rng.range_int(1, 2) as f64
+            } else {
+                end + rng.range(0.3, 2.0)
+            };
+            poly = poly * linear_factor(root);
+            remaining -= 1;
+        }
+    }
+    let scale = if integer {
+        rng.range_int(1, 2) as f64
+    } else {
+        rng.range(0.3, 1.5)
+    };
+    let sign = if rng.chance(0.5) { 1.0 } else { -1.0 };
+    Polynomial::new(
+        (&poly * (sign * scale))
+            .coefs()
+            .iter()
+            .map(|c| settings.round(*c)),
+    )
+}
+
+/// The polynomial `±x^(n-2)(x - r1)(x - r2)` with `r1 < r2 < 0`, which always has few, small coefficients.
+fn fallback_poly(settings: &GeneratorSettings, rng: &mut Rng) -> Polynomial {
+    let start = -(rng.range_int(2, 3) as f64);
+    let mut poly = linear_factor(start) * linear_factor(-1.0);
+    for _ in 2..settings.degree {
+        poly = poly * Polynomial::x();
+    }
+    if rng.chance(0.5) {
+        poly = -poly;
+    }
+    poly
+}
+
+/// The polynomial `x - root`.
+fn linear_factor(root: f64) -> Polynomial {
+    Polynomial::new([1.0, -root])
+}
@@ -0,0 +1,117 @@
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What a hint gives the player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HintKind {
+    /// A tip written by the author of the level.
+    Text(String),
+    /// The value of the parameter at this position of the enemy path, see [`crate::curve::Curve::params`].
+    RevealCoefficient(usize),
+    /// Marks where the main component of the enemy path is zero, i.e. where it starts and lands.
+    ShowRoots,
+    /// Reveals this fraction of the path more than the time taken does.
+    RevealPath(f64),
+}
+
+impl HintKind {
+    /// One hint of every kind, to start a new hint with.
+    pub fn examples() -> [HintKind; 4] {
+        [
+            HintKind::Text(String::new()),
+            HintKind::RevealCoefficient(0),
+            HintKind::ShowRoots,
+            HintKind::RevealPath(0.1),
+        ]
+    }
+
+    /// The time in seconds that the hint costs, unless the level sets another cost.
+    pub fn default_cost(&self) -> f64 {
+        match self {
+            HintKind::Text(_) => 2.0,
+            HintKind::RevealCoefficient(_) => 10.0,
+            HintKind::ShowRoots => 5.0,
+            HintKind::RevealPath(_) => 5.0,
+        }
+    }
+
+    /// Whether the other hint is of the same kind, regardless of what it reveals.
+    pub fn same_kind(&self, other: &HintKind) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl fmt::Display for HintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HintKind::Text(_) => "Text",
+            HintKind::RevealCoefficient(_) => "Reveal coefficient",
+            HintKind::ShowRoots => "Show roots",
+            HintKind::RevealPath(_) => "Reveal path",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A hint of a level, which the player can buy with time.
+///
+/// In level files, a hint is either written in full, like `(kind: ShowRoots, cost: 3.0)`,
+/// or as just a string for a text hint with the default cost.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hint {
+    pub kind: HintKind,
+    /// Time in seconds that is added to the time taken when the hint is used.
+    pub cost: f64,
+}
+
+impl Hint {
+    pub fn new(kind: HintKind) -> Self {
+        Self {
+            cost: kind.default_cost(),
+            kind,
+        }
+    }
+}
+
+/// The fields of a [`Hint`] as written in level files, where the cost can be left out.
+#[derive(Deserialize)]
+struct HintFields {
+    kind: HintKind,
+    // Written as a plain number, not as `Some(cost)`.
+    #[serde(default, deserialize_with = "deserialize_cost")]
+    cost: Option<f64>,
+}
+
+fn deserialize_cost<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    f64::deserialize(deserializer).map(Some)
+}
+
+impl<'de> Deserialize<'de> for Hint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HintVisitor;
+
+        impl<'de> Visitor<'de> for HintVisitor {
+            type Value = Hint;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a hint or a string")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Hint, E> {
+                Ok(Hint::new(HintKind::Text(text.to_string())))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Hint, A::Error> {
+                let fields = HintFields::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                let mut hint = Hint::new(fields.kind);
+                if let Some(cost) = fields.cost {
+                    hint.cost = cost;
+                }
+                Ok(hint)
+            }
+        }
+
+        deserializer.deserialize_any(HintVisitor)
+    }
+}
@@ -1,19 +1,50 @@
 use bevy::math::Vec2;
 
+use crate::curve::{Curve, CurveKind};
+use crate::hint::{Hint, HintKind};
+use crate::math::{Matrix, Qr};
+use crate::polynomial::Polynomial;
+use crate::score::StarThresholds;
+use crate::win_condition::WinCondition;
+
+/// How far each coefficient of the prediction can be from the enemy path to win,
+/// with the default [`WinCondition`].
+pub const WIN_TOLERANCE: f64 = 0.01;
+
+/// Time in seconds that a snap fit costs, unless the level sets another cost.
+pub const DEFAULT_SNAP_FIT_COST: f64 = 5.0;
+
+/// Time in seconds that is added to the time taken when a wrong prediction is confirmed.
+pub const WRONG_CONFIRM_PENALTY: f64 = 1.0;
+
+/// Two handles closer together than this (horizontally) can't be interpolated.
+const HANDLE_SPACING: f64 = 1e-6;
+
 /// A Level contains all the information needed to setup the plot
 pub struct Level {
-    /// Coefficients of the polynomial that describes the path of the enemy
-    pub enemy_coefs: Vec<f64>,
-    /// Coefficients of the polynomial that describes the path of the player
-    pub player_coefs: Vec<f64>,
+    /// The path of the enemy
+    pub enemy_path: Curve,
+    /// The path predicted by the player, always of the same shape as the enemy path
+    pub player_path: Curve,
     /// Used to determine which area of the plot to show
     pub limits: [Vec2; 2],
     /// Time in seconds to complete the game.
     pub max_time: f64,
     /// Time in seconds that have passed since the start of the level.
     pub time_taken: f64,
-    start_x: f64,
-    end_x: f64,
+    /// When the prediction is close enough to win.
+    pub win_condition: WinCondition,
+    /// Time in seconds that is added to the time taken for each [`Level::snap_fit`].
+    pub snap_fit_cost: f64,
+    /// The scores needed for each star.
+    pub stars: StarThresholds,
+    /// Number of times that a wrong prediction was confirmed.
+    pub wrong_confirms: usize,
+    hints: Vec<Hint>,
+    /// The positions in `hints` of the hints that were used, in the order they were used.
+    hints_used: Vec<usize>,
+    start: f64,
+    end: f64,
     /// The player has won this level (used for UI)
     pub won: bool,
     /// The player has lost i.e. time_taken > max_time
@@ -23,148 +54,365 @@ pub struct Level {
 impl Default for Level {
     fn default() -> Self {
         Self {
-            enemy_coefs: vec![-1.0, 0.0, 1.0],
-            player_coefs: vec![1.0; 3],
+            enemy_path: Curve::graph(Box::new(Polynomial::new([-1.0, 0.0, 1.0]))),
+            player_path: Curve::graph(Box::new(Polynomial::filled(1.0, 3))),
             limits: [Vec2::new(-2., -1.), Vec2::new(2., 2.)],
             max_time: 20.0,
             time_taken: 0.0,
-            start_x: -1.0,
-            end_x: 1.0,
+            win_condition: WinCondition::default(),
+            snap_fit_cost: DEFAULT_SNAP_FIT_COST,
+            stars: StarThresholds::default(),
+            wrong_confirms: 0,
+            hints: Vec::new(),
+            hints_used: Vec::new(),
+            start: -1.0,
+            end: 1.0,
             won: false,
             lost: false,
         }
     }
 }
 impl Level {
-    fn eval_poly(x: f64, coefs: &Vec<f64>) -> f64 {
-        let mut y = 0.;
-        for coef in coefs {
-            y = coef + x * y;
-        }
-        y
-    }
-
-    fn min_max(start: f64, end: f64, coefs: &Vec<f64>) -> [f64; 2] {
-        let mut min = Level::eval_poly(start, coefs);
-        let mut max = min;
-        for x in LinSpace::new(start, end, 0.01) {
-            let y = Level::eval_poly(x, coefs);
-            if y < min {
-                min = y;
-            } else if y > max {
-                max = y;
-            }
-        }
-        [min, max]
-    }
-
-    fn bin_search_root(mut start: f64, mut end: f64, coefs: &Vec<f64>) -> f64 {
-        let mut y_val = Level::eval_poly(start, coefs);
-        let start_is_negative = y_val <= 0.0;
-        let mut mid = start;
-        let mut count_max = 30;
-        while y_val.abs() > 1e-5 && count_max > 0 {
-            mid = (start + end) / 2.;
-            y_val = Level::eval_poly(mid, coefs);
-            if start_is_negative == (y_val <= 0.0) {
-                start = mid;
-            } else {
-                end = mid;
-            }
-            count_max -= 1;
-        }
-        if count_max <= 0 {
-            eprintln!("Count Max Happened!");
-        }
-        mid
-    }
-
-    fn get_roots(coefs: &Vec<f64>) -> Vec<f64> {
-        let mut roots = Vec::new();
-        if coefs.len() < 1 {
-            return roots;
-        }
-        let mut y_is_negative = Level::eval_poly(-10., coefs) <= 0.0;
-        let mut prev_x = 0.0;
-        for x in LinSpace::new(-10., 10., 0.1) {
-            let y = Level::eval_poly(x, coefs);
-            if y_is_negative != (y <= 0.0) {
-                // Changed sign so there is a root.
-                roots.push(Level::bin_search_root(prev_x.clone(), x.clone(), coefs));
-            }
-            y_is_negative = y <= 0.0;
-            prev_x = x;
-        }
-        roots
-    }
-
-    pub fn new(enemy_coefs: impl IntoIterator<Item = f64>, max_time: f64) -> Result<Self, String> {
-        let enemy_coefs = enemy_coefs.into_iter().collect::<Vec<f64>>();
-        let player_coefs = vec![1.0; enemy_coefs.len()];
-        let roots = Level::get_roots(&enemy_coefs);
+    pub fn new(enemy_path: Curve, max_time: f64) -> Result<Self, String> {
+        let mut player_path = enemy_path.clone();
+        player_path.reset();
+        let roots = enemy_path.main_component().roots();
         if roots.len() < 2 {
-            return Err("Needs at least 2 roots.".to_string());
+            return Err("Needs at least 2 roots where the path crosses zero.".to_string());
         }
-        let start_x = roots[0];
-        let end_x = roots[1];
-        let min_max = Level::min_max(start_x, end_x, &enemy_coefs);
+        let start = roots[0];
+        let end = roots[1];
+        let [min, max] = enemy_path.bounds(start, end);
         Ok(Self {
-            enemy_coefs,
-            player_coefs,
+            enemy_path,
+            player_path,
             limits: [
-                Vec2::new(start_x as f32 - 1.0, min_max[0] as f32 - 1.0),
-                Vec2::new(end_x as f32 + 1.0, min_max[1] as f32 + 1.0),
+                Vec2::new(min[0] as f32 - 1.0, min[1] as f32 - 1.0),
+                Vec2::new(max[0] as f32 + 1.0, max[1] as f32 + 1.0),
             ],
             max_time,
-            start_x,
-            end_x,
+            start,
+            end,
             ..Default::default()
         })
     }
 
+    /// Continues this level as `level`, e.g. the same level after its file has been edited.
+    /// The prediction, time taken, wrong confirms and used hints are kept where possible.
+    pub fn carry_over(&self, mut level: Level) -> Level {
+        if level.player_path.same_shape(&self.player_path) {
+            level.player_path = self.player_path.clone();
+        }
+        level.time_taken = self.time_taken.min(level.max_time);
+        level.lost = level.time_taken >= level.max_time;
+        level.wrong_confirms = self.wrong_confirms;
+        // Only changed hints are reset, so used hints stay used.
+        if level.hints == self.hints {
+            level.hints_used = self.hints_used.clone();
+        }
+        level
+    }
+
     pub fn restart(&mut self) {
         self.time_taken = 0.;
-        self.player_coefs = vec![1.0; self.player_coefs.len()];
+        self.wrong_confirms = 0;
+        self.hints_used.clear();
+        self.player_path.reset();
         self.won = false;
         self.lost = false;
     }
 
+    /// Lets `seconds` of game time pass, after which the level is lost if the time is up.
+    pub fn advance_time(&mut self, seconds: f64) {
+        if self.won || self.lost {
+            return;
+        }
+        self.time_taken += seconds;
+        if self.time_taken >= self.max_time {
+            self.lost = true;
+        }
+    }
+
     pub fn check_won(&mut self) -> bool {
-        let mut res = true;
-        for i in 0..self.enemy_coefs.len() {
-            if (self.enemy_coefs[i] - self.player_coefs[i]).abs() > 0.01 {
-                res = false;
-                break;
+        self.won = self.win_condition.is_met(self);
+        self.won
+    }
+
+    /// Confirms the prediction, which wins the level if it meets the win condition.
+    /// Otherwise the mistake costs [`WRONG_CONFIRM_PENALTY`].
+    pub fn confirm(&mut self) -> bool {
+        if self.check_won() {
+            return true;
+        }
+        self.time_taken += WRONG_CONFIRM_PENALTY;
+        self.wrong_confirms += 1;
+        false
+    }
+
+    /// How far the prediction is from the enemy path, measured by the win condition.
+    pub fn prediction_error(&self) -> f64 {
+        self.win_condition.error(self)
+    }
+
+    pub fn hints(&self) -> &[Hint] {
+        &self.hints
+    }
+
+    /// Replaces the hints, which are all unused again.
+    /// Fails if a hint doesn't fit the enemy path.
+    pub fn set_hints(&mut self, hints: Vec<Hint>) -> Result<(), String> {
+        let count = self.enemy_path.params().len();
+        for hint in &hints {
+            match hint.kind {
+                HintKind::RevealCoefficient(i) if i >= count => {
+                    return Err(format!(
+                        "A hint reveals coefficient {}, but the path only has {}.",
+                        i, count
+                    ))
+                }
+                HintKind::RevealPath(fraction) if !(fraction > 0.0 && fraction <= 1.0) => {
+                    return Err(
+                        "A hint reveals a fraction of the path that isn't between 0 and 1."
+                            .to_string(),
+                    )
+                }
+                _ => {}
+            }
+            if hint.cost < 0.0 {
+                return Err("A hint has a negative cost.".to_string());
             }
         }
-        self.won = res;
-        res
+        self.hints = hints;
+        self.hints_used.clear();
+        Ok(())
+    }
+
+    /// The positions of the hints that were used, in the order they were used.
+    pub fn hints_used(&self) -> &[usize] {
+        &self.hints_used
     }
 
-    pub fn eval_enemy_poly(&self, x: f64) -> f64 {
-        Level::eval_poly(x, &self.enemy_coefs)
+    pub fn is_hint_used(&self, i: usize) -> bool {
+        self.hints_used.contains(&i)
+    }
+
+    /// Uses the hint at position `i`, which adds its cost to the time taken.
+    /// Returns `false` if there is no such hint, or if it was used already.
+    pub fn use_hint(&mut self, i: usize) -> bool {
+        if i >= self.hints.len() || self.is_hint_used(i) {
+            return false;
+        }
+        self.hints_used.push(i);
+        self.time_taken += self.hints[i].cost;
+        true
     }
-    pub fn eval_player_poly(&self, x: f64) -> f64 {
-        Level::eval_poly(x, &self.player_coefs)
+
+    /// The time in seconds that the used hints cost.
+    pub fn hint_cost(&self) -> f64 {
+        self.hints_used.iter().map(|&i| self.hints[i].cost).sum()
+    }
+
+    /// Whether a used hint shows the roots of the enemy path.
+    pub fn shows_roots(&self) -> bool {
+        self.hints_used
+            .iter()
+            .any(|&i| self.hints[i].kind == HintKind::ShowRoots)
+    }
+
+    /// The fraction of the path that used hints reveal on top of the time taken.
+    fn revealed_by_hints(&self) -> f64 {
+        self.hints_used
+            .iter()
+            .map(|&i| match self.hints[i].kind {
+                HintKind::RevealPath(fraction) => fraction,
+                _ => 0.0,
+            })
+            .sum()
     }
 
-    /// Returns essentially an iterator that has points
-    /// evenly spaced from the start of the poly to the end, but cut off at the
+    /// What the hint at position `i` tells the player once it is used.
+    pub fn hint_text(&self, i: usize) -> String {
+        match &self.hints[i].kind {
+            HintKind::Text(text) => text.clone(),
+            HintKind::RevealCoefficient(k) => format!(
+                "{} = {:.2}",
+                self.enemy_path.param_names()[*k],
+                self.enemy_path.params()[*k]
+            ),
+            HintKind::ShowRoots => format!(
+                "The path goes from {} = {:.2} to {:.2}.",
+                self.enemy_path.kind().variable(),
+                self.start,
+                self.end
+            ),
+            HintKind::RevealPath(fraction) => {
+                format!("{:.0}% more of the path is revealed.", fraction * 100.0)
+            }
+        }
+    }
+
+    /// The parameter where the path of the enemy starts, i.e. the first root of its main component.
+    /// For graphs this is the x coordinate.
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    /// The parameter where the path of the enemy ends, i.e. the second root of its main component.
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn enemy_point(&self, t: f64) -> [f64; 2] {
+        self.enemy_path.point(t)
+    }
+    pub fn player_point(&self, t: f64) -> [f64; 2] {
+        self.player_path.point(t)
+    }
+
+    /// Whether the prediction is the graph of a polynomial,
+    /// which can be set with handles on the plot or with a snap fit.
+    pub fn is_polynomial_graph(&self) -> bool {
+        self.player_path.kind() == CurveKind::Graph
+            && self.player_path.main_component().as_polynomial().is_some()
+    }
+
+    /// Evenly spaced points on the prediction across the visible area, one for each coefficient,
+    /// so the prediction is the polynomial through them.
+    pub fn prediction_handles(&self) -> Vec<[f64; 2]> {
+        let count = self.player_path.params().len();
+        let [min, max] = [self.limits[0].x as f64, self.limits[1].x as f64];
+        (0..count)
+            .map(|i| {
+                // Keep the handles away from the edges, where they are hard to grab.
+                let x = min + (max - min) * (i as f64 + 1.0) / (count as f64 + 1.0);
+                self.player_point(x)
+            })
+            .collect()
+    }
+
+    /// Sets the prediction to the polynomial through the handles, see [`interpolate`].
+    ///
+    /// Returns `false` and keeps the prediction if the path isn't a graph of a polynomial with a coefficient for each handle,
+    /// if two handles are above each other, or if a coefficient would be outside the range of the controls.
+    pub fn fit_handles(&mut self, handles: &[[f64; 2]]) -> bool {
+        if !self.is_polynomial_graph() || handles.len() != self.player_path.params().len() {
+            return false;
+        }
+        let mut poly = match interpolate(handles) {
+            Some(poly) => poly,
+            None => return false,
+        };
+        poly.pad_to(handles.len());
+        let component = &mut self.player_path.components_mut()[0];
+        let in_range = poly
+            .coefs()
+            .iter()
+            .enumerate()
+            .all(|(i, coef)| component.param_range(i).contains(coef));
+        if in_range {
+            component.params_mut().copy_from_slice(poly.coefs());
+        }
+        in_range
+    }
+
+    /// Sets the prediction to the least squares fit of the points marked by the player, for [`Level::snap_fit_cost`].
+    /// The coefficients are limited to the range of the controls.
+    ///
+    /// Returns `false` without the time cost if the path isn't a graph of a polynomial,
+    /// or if there aren't enough different points to determine the fit.
+    pub fn snap_fit(&mut self, points: &[[f64; 2]]) -> bool {
+        let len = self.player_path.params().len();
+        if !self.is_polynomial_graph() || points.len() < len {
+            return false;
+        }
+        let xs = points.iter().map(|point| point[0]).collect::<Vec<_>>();
+        if distinct_count(&xs) < len {
+            return false;
+        }
+        let ys = points.iter().map(|point| point[1]).collect::<Vec<_>>();
+        let fit = match Qr::new(&Matrix::vandermonde(&xs, len)).solve_least_squares(&ys) {
+            Some(fit) => fit,
+            None => return false,
+        };
+        let component = &mut self.player_path.components_mut()[0];
+        for (i, coef) in fit.into_iter().enumerate() {
+            let range = component.param_range(i);
+            component.params_mut()[i] = coef.clamp(*range.start(), *range.end());
+        }
+        self.time_taken += self.snap_fit_cost;
+        true
+    }
+
+    /// Returns essentially an iterator that has parameters
+    /// evenly spaced from the start of the path to the end, but cut off at the
     /// `time` value.
     pub fn domain_range_time(&self, spacing: f64) -> LinSpace {
-        // Lerp the end value between start_x and end_x using time as factor.
+        // Lerp the end value between start and end using time as factor.
+        let fraction = (self.time_taken / self.max_time + self.revealed_by_hints()).min(1.0);
         LinSpace::new(
-            self.start_x,
-            self.start_x + (self.end_x - self.start_x) * self.time_taken / self.max_time,
+            self.start,
+            self.start + (self.end - self.start) * fraction,
             spacing,
         )
     }
 
-    pub fn domain_range_limits(&self, spacing: f64) -> LinSpace {
-        LinSpace::new(self.limits[0].x as f64, self.limits[1].x as f64, spacing)
+    /// Returns an iterator over the whole path of the enemy, as if the time was up.
+    pub fn domain_range_path(&self, spacing: f64) -> LinSpace {
+        LinSpace::new(self.start, self.end, spacing)
+    }
+
+    /// Returns an iterator over the parameters at which the prediction is drawn:
+    /// the whole visible area for graphs, and the path of the enemy otherwise.
+    pub fn domain_range_prediction(&self, spacing: f64) -> LinSpace {
+        match self.enemy_path.kind() {
+            CurveKind::Graph => {
+                LinSpace::new(self.limits[0].x as f64, self.limits[1].x as f64, spacing)
+            }
+            _ => self.domain_range_path(spacing),
+        }
     }
 }
 
+/// The number of values that are more than [`HANDLE_SPACING`] apart.
+fn distinct_count(xs: &[f64]) -> usize {
+    let mut xs = xs.to_vec();
+    xs.sort_by(f64::total_cmp);
+    let gaps = xs
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] >= HANDLE_SPACING)
+        .count();
+    gaps + !xs.is_empty() as usize
+}
+
+/// The polynomial of the lowest degree through the points,
+/// or `None` if there are no points or two of them have (nearly) the same x coordinate.
+///
+/// Uses Newton's divided differences on the points sorted by x, which is stable for the few points of a level.
+pub fn interpolate(points: &[[f64; 2]]) -> Option<Polynomial> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    if points.is_empty()
+        || points
+            .windows(2)
+            .any(|pair| pair[1][0] - pair[0][0] < HANDLE_SPACING)
+    {
+        return None;
+    }
+    let xs = points.iter().map(|point| point[0]).collect::<Vec<_>>();
+    let mut differences = points.iter().map(|point| point[1]).collect::<Vec<_>>();
+    for j in 1..xs.len() {
+        for i in (j..xs.len()).rev() {
+            differences[i] = (differences[i] - differences[i - 1]) / (xs[i] - xs[i - j]);
+        }
+    }
+    // Expand the Newton form `d0 + (x - x0)(d1 + (x - x1)(d2 + ...))` from the inside out.
+    let mut poly = Polynomial::constant(*differences.last().unwrap());
+    for (x, difference) in xs.iter().zip(&differences).rev().skip(1) {
+        poly = &(&poly * &Polynomial::new([1.0, -x])) + &Polynomial::constant(*difference);
+    }
+    Some(poly)
+}
+
 /// Assumes [`f64`] for now.
 pub struct LinSpace {
     end: f64,
@@ -195,3 +443,59 @@ impl Iterator for LinSpace {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carry_over_keeps_the_players_progress() {
+        let path = |coefs: &[f64]| Curve::graph(Box::new(Polynomial::new(coefs.iter().copied())));
+        let hints = vec![
+            Hint::new(HintKind::ShowRoots),
+            Hint::new(HintKind::RevealCoefficient(0)),
+        ];
+        let mut level = Level::new(path(&[-1.0, 0.0, 1.0]), 60.0).unwrap();
+        level.set_hints(hints.clone()).unwrap();
+        level.player_path.components_mut()[0]
+            .params_mut()
+            .copy_from_slice(&[1.0, 2.0, 3.0]);
+        level.use_hint(1);
+        level.confirm();
+        let time_taken = level.time_taken;
+
+        let mut edited = Level::new(path(&[-2.0, 0.0, 2.0]), 60.0).unwrap();
+        edited.set_hints(hints.clone()).unwrap();
+        let edited = level.carry_over(edited);
+        assert_eq!(edited.enemy_path.params(), [-2.0, 0.0, 2.0]);
+        assert_eq!(edited.player_path.params(), [1.0, 2.0, 3.0]);
+        assert_eq!(edited.time_taken, time_taken);
+        assert_eq!(edited.wrong_confirms, 1);
+        assert_eq!(edited.hints_used(), [1]);
+
+        // Changed hints start unused, a prediction of another degree starts over,
+        // and a shorter time limit ends the level.
+        let mut edited = Level::new(path(&[1.0, 0.0, -1.0, 0.0]), time_taken).unwrap();
+        edited.set_hints(hints[..1].to_vec()).unwrap();
+        let edited = level.carry_over(edited);
+        assert!(edited.hints_used().is_empty());
+        assert_eq!(edited.player_path.params(), [1.0; 4]);
+        assert!(edited.lost);
+    }
+
+    #[test]
+    fn snap_fit_needs_distinct_points() {
+        let mut level = Level::default();
+        // Four points, but only two different x values for three coefficients.
+        let points = [[0.5, 0.0], [0.5, 0.0], [0.5 + 1e-12, 0.0], [1.0, 1.0]];
+        assert!(!level.snap_fit(&points));
+        assert_eq!(level.time_taken, 0.0);
+
+        let points = [[-1.0, 0.0], [0.0, -1.0], [1.0, 0.0], [0.5, -0.75]];
+        assert!(level.snap_fit(&points));
+        assert_eq!(level.time_taken, level.snap_fit_cost);
+        for (fit, coef) in level.player_path.params().iter().zip([1.0, 0.0, -1.0]) {
+            assert!((fit - coef).abs() < 1e-9, "{} != {}", fit, coef);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::world::{FromWorld, World},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::curve::{Curve, CurveKind};
+use crate::expression::parse_polynomial;
+use crate::hint::Hint;
+use crate::level::{Level, DEFAULT_SNAP_FIT_COST};
+use crate::score::StarThresholds;
+use crate::trajectory::{Trajectory, TrajectoryFamily};
+use crate::win_condition::WinCondition;
+
+/// The description of a level, as stored in a `.level.ron` file in `assets/levels/`.
+///
+/// ```ron
+/// (
+///     pack: "Level 1",
+///     title: "Warm Up",
+///     difficulty: "Easy",
+///     coefficients: [-1.0, 0.0, 1.0],
+///     max_time: 100.0,
+///     hints: ["The path is symmetric.", (kind: ShowRoots, cost: 3.0)],
+///     win_condition: (criterion: MaxDeviation, tolerance: 0.05),
+///     stars: (one: 500, two: 1100, three: 1600),
+/// )
+/// ```
+///
+/// A parametric curve that loops back, with `x(t) = t^3 - 3t` and `y(t) = 4 - t^2`:
+///
+/// ```ron
+/// (
+///     pack: "Level 4",
+///     title: "Loop",
+///     difficulty: "Hard",
+///     curve: Parametric,
+///     x_component: Some((coefficients: [1.0, 0.0, -3.0, 0.0])),
+///     coefficients: [-1.0, 0.0, 4.0],
+///     max_time: 120.0,
+/// )
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "6c1a3f0e-58e2-4b8b-9d2c-3e6f1f4c2a71"]
+pub struct LevelDefinition {
+    /// Levels with the same pack are shown together in the level menu.
+    pub pack: String,
+    pub title: String,
+    /// Name of the difficulty, e.g. "Easy".
+    pub difficulty: String,
+    /// How the enemy path is drawn, defaults to the graph of a function.
+    #[serde(default)]
+    pub curve: CurveKind,
+    /// The kind of function of the main component of the enemy path (`y`, or `r` for polar curves),
+    /// defaults to a polynomial.
+    #[serde(default)]
+    pub family: TrajectoryFamily,
+    /// Parameters of the main component of the enemy path, see [`TrajectoryFamily`].
+    /// For polynomials these are the coefficients, from the highest degree to the constant term,
+    /// which can also be written as an equation like `"(x - 1)(x + 1)"`.
+    #[serde(deserialize_with = "coefficients_or_equation")]
+    pub coefficients: Vec<f64>,
+    /// The `x` component of parametric curves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_component: Option<ComponentDefinition>,
+    /// Time in seconds to complete the level.
+    pub max_time: f64,
+    /// Hints that the player can buy with time, in the order they are offered.
+    #[serde(default)]
+    pub hints: Vec<Hint>,
+    /// Defaults to comparing the coefficients.
+    #[serde(default)]
+    pub win_condition: WinCondition,
+    /// Time in seconds that a snap fit costs, see [`Level::snap_fit`].
+    #[serde(default = "default_snap_fit_cost")]
+    pub snap_fit_cost: f64,
+    /// The scores needed for one, two and three stars.
+    #[serde(default)]
+    pub stars: StarThresholds,
+}
+
+fn default_snap_fit_cost() -> f64 {
+    DEFAULT_SNAP_FIT_COST
+}
+
+impl LevelDefinition {
+    /// Creates the enemy path described by this definition.
+    pub fn curve(&self) -> Result<Curve, String> {
+        let main = self.family.build(&self.coefficients)?;
+        match self.curve {
+            CurveKind::Graph => Ok(Curve::graph(main)),
+            CurveKind::Parametric => {
+                let x = self
+                    .x_component
+                    .as_ref()
+                    .ok_or("Parametric curves need an x component.")?;
+                Ok(Curve::parametric(x.trajectory()?, main))
+            }
+            CurveKind::Polar => Ok(Curve::polar(main)),
+        }
+    }
+
+    /// Checks the numbers that a level can't be played without,
+    /// as definitions also come from share codes and other places that can't be trusted.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.max_time.is_finite() && self.max_time > 0.0) {
+            return Err(format!(
+                "The time limit must be positive, not {}.",
+                self.max_time
+            ));
+        }
+        let tolerance = self.win_condition.tolerance;
+        if !(tolerance.is_finite() && tolerance > 0.0) {
+            return Err(format!(
+                "The tolerance must be positive, not {}.",
+                tolerance
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates the level described by this definition.
+    pub fn to_level(&self) -> Result<Level, String> {
+        self.validate()?;
+        let mut level = Level::new(self.curve()?, self.max_time)?;
+        level.win_condition = self.win_condition;
+        level.snap_fit_cost = self.snap_fit_cost;
+        level.stars = self.stars;
+        level.set_hints(self.hints.clone())?;
+        Ok(level)
+    }
+
+    /// The definition of a level, e.g. a generated one, without a pack, title or difficulty.
+    pub fn from_level(level: &Level) -> Self {
+        let path = &level.enemy_path;
+        let main = path.main_component();
+        Self {
+            pack: String::new(),
+            title: String::new(),
+            difficulty: String::new(),
+            curve: path.kind(),
+            family: main.family(),
+            coefficients: main.params().to_vec(),
+            x_component: match path.kind() {
+                CurveKind::Parametric => {
+                    let x = &path.components()[0];
+                    Some(ComponentDefinition {
+                        family: x.family(),
+                        coefficients: x.params().to_vec(),
+                    })
+                }
+                CurveKind::Graph | CurveKind::Polar => None,
+            },
+            max_time: level.max_time,
+            hints: level.hints().to_vec(),
+            win_condition: level.win_condition,
+            snap_fit_cost: level.snap_fit_cost,
+            stars: level.stars,
+        }
+    }
+
+    /// The contents of the level file for this definition.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+    }
+}
+
+/// A component of the enemy path other than the main one, see [`LevelDefinition`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentDefinition {
+    #[serde(default)]
+    pub family: TrajectoryFamily,
+    #[serde(deserialize_with = "coefficients_or_equation")]
+    pub coefficients: Vec<f64>,
+}
+
+impl ComponentDefinition {
+    pub fn trajectory(&self) -> Result<Box<dyn Trajectory>, String> {
+        self.family.build(&self.coefficients)
+    }
+}
+
+/// Reads a list of coefficients, or the equation of a polynomial, see [`parse_polynomial`].
+fn coefficients_or_equation<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<f64>, D::Error> {
+    struct CoefficientsVisitor;
+
+    impl<'de> Visitor<'de> for CoefficientsVisitor {
+        type Value = Vec<f64>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a list of coefficients or an equation")
+        }
+
+        fn visit_str<E: de::Error>(self, equation: &str) -> Result<Vec<f64>, E> {
+            parse_polynomial(equation)
+                .map(|poly| poly.normalized().coefs().to_vec())
+                .map_err(|err| E::custom(format!("Invalid equation \"{}\": {}", equation, err)))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<f64>, A::Error> {
+            let mut coefficients = Vec::new();
+            while let Some(coefficient) = seq.next_element()? {
+                coefficients.push(coefficient);
+            }
+            Ok(coefficients)
+        }
+    }
+
+    deserializer.deserialize_any(CoefficientsVisitor)
+}
+
+/// The problems with the level files that are currently loaded, by file path.
+///
+/// Level files are loaded in the background, so the loader reports its errors here
+/// for the UI to show them.
+#[derive(Debug, Default, Clone)]
+pub struct LevelFileErrors(Arc<Mutex<BTreeMap<PathBuf, String>>>);
+
+impl LevelFileErrors {
+    pub fn set(&self, path: &Path, error: impl Display) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), error.to_string());
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.0.lock().unwrap().remove(path);
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// Returns the errors as `(path, error)` pairs, sorted by path.
+    pub fn get(&self) -> Vec<(PathBuf, String)> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, error)| (path.clone(), error.clone()))
+            .collect()
+    }
+}
+
+/// Loads a [`LevelDefinition`], and checks that it describes a valid level.
+/// Problems are reported to the [`LevelFileErrors`] resource.
+pub struct LevelDefinitionLoader {
+    errors: LevelFileErrors,
+}
+
+impl FromWorld for LevelDefinitionLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            errors: world
+                .get_resource_or_insert_with(LevelFileErrors::default)
+                .clone(),
+        }
+    }
+}
+
+impl AssetLoader for LevelDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let path = load_context.path();
+            let definition = match ron::de::from_bytes::<LevelDefinition>(bytes) {
+                Ok(definition) => definition,
+                Err(err) => {
+                    self.errors.set(path, &err);
+                    return Err(err.into());
+                }
+            };
+            match definition.to_level() {
+                Ok(_) => self.errors.remove(path),
+                Err(err) => self.errors.set(path, err),
+            }
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// A list of level files, relative to the assets folder.
+///
+/// The web build can't list the files in a folder,
+/// so it uses `assets/levels/levels.index.ron` to find the level files instead.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "0f5b2d7c-9a41-4c3e-8e1b-7d9a6c5e4b32"]
+pub struct LevelIndex(pub Vec<String>);
+
+#[derive(Default)]
+pub struct LevelIndexLoader;
+
+impl AssetLoader for LevelIndexLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let index = ron::de::from_bytes::<LevelIndex>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(index));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["index.ron"]
+    }
+}
@@ -0,0 +1,24 @@
+//! The game logic of Equata, independent of the user interface.
+
+pub mod campaign;
+pub mod clock;
+pub mod curve;
+pub mod daily;
+pub mod difficulty;
+pub mod expression;
+pub mod generator;
+pub mod hint;
+pub mod level;
+pub mod level_file;
+pub mod math;
+pub mod polynomial;
+pub mod polynomial_form;
+pub mod profile;
+pub mod replay;
+pub mod score;
+pub mod share_code;
+pub mod solvability;
+pub mod storage;
+pub mod survival;
+pub mod trajectory;
+pub mod win_condition;
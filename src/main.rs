@@ -1,44 +1,339 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{HandleId, LoadState},
+    prelude::*,
+};
 use bevy_egui::{
     egui::{
         self,
-        plot::{Legend, Points},
+        plot::{Legend, MarkerShape, Points},
         Color32, CtxRef, FontFamily, Frame,
     },
     EguiContext, EguiPlugin, EguiSettings,
 };
 use bevy_kira_audio::{Audio, AudioPlugin};
 use egui::plot::{Line, Plot, Value, Values};
+use std::collections::{HashMap, HashSet};
+
+use equata::campaign::{Campaign, CampaignLoader, Unlock};
+use equata::clock::{GameClock, TIME_STEP};
+use equata::curve::CurveKind;
+use equata::daily::{share_text, Date};
+use equata::difficulty::DifficultyEstimate;
+use equata::expression::{parse_coefficients, ParseError};
+use equata::generator::{generate_level, Difficulty, GeneratorSettings, Rng};
+use equata::level::Level;
+use equata::level_file::{
+    LevelDefinition, LevelDefinitionLoader, LevelFileErrors, LevelIndex, LevelIndexLoader,
+};
+use equata::polynomial::Polynomial;
+use equata::polynomial_form::PolynomialForm;
+use equata::profile::{Profile, Settings};
+use equata::replay::{Replay, ReplayEvent};
+use equata::score::{star_text, LevelResult};
+use equata::share_code;
+use equata::solvability::SolvabilityReport;
+use equata::survival::{add_to_leaderboard, SurvivalRun};
+use equata::trajectory::Trajectory;
 
-mod level;
-use level::Level;
+mod editor;
+use editor::{ui_level_editor, LevelEditor};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum AppState {
     MainMenu,
+    WorldMap,
     LevelMenu,
     About,
     InGame,
     Paused,
+    LevelEditor,
+    /// Watching a [`ReplayPlayer`].
+    Replay,
+}
+
+/// How the current level was started, which decides where to go once it is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum GameMode {
+    /// A level from the level menu.
+    #[default]
+    Levels,
+    /// A level that is being made in the level editor.
+    PlayTest,
+    /// A randomly generated level, see [`RandomLevels`].
+    Random,
+    /// A level of a [`SurvivalRun`].
+    Survival,
+    /// The level of a [`DailyChallenge`].
+    Daily,
+}
+
+impl GameMode {
+    /// Whether the results are compared with other runs,
+    /// so the level has to be played at normal [`GameClock`] speed.
+    fn is_ranked(self) -> bool {
+        matches!(self, GameMode::Survival | GameMode::Daily)
+    }
+}
+
+/// The "Enter Code" dialog of the level menu, for levels shared with [`share_code::encode`].
+#[derive(Default)]
+struct CodeDialog {
+    open: bool,
+    code: String,
+    /// A ghost for the level, see [`read_ghost_file`].
+    ghost_file: String,
+    error: Option<String>,
+}
+
+impl CodeDialog {
+    /// The level of the code, which may also be in a link. Shows the error otherwise.
+    fn level(&mut self) -> Option<Level> {
+        let code = share_code::code_from_link(&self.code).unwrap_or(&self.code);
+        match share_code::decode(code).and_then(|definition| definition.to_level()) {
+            Ok(level) => {
+                self.error = None;
+                Some(level)
+            }
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+/// Reads the replay at `path`, e.g. one that was sent along with a share code, to use as a [`Ghost`].
+#[cfg(not(target_arch = "wasm32"))]
+fn read_ghost_file(path: &str) -> Result<Replay, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+    Replay::from_ron(&text)
+}
+
+/// The web has no files to read, so the contents of the replay are pasted instead.
+#[cfg(target_arch = "wasm32")]
+fn read_ghost_file(text: &str) -> Result<Replay, String> {
+    Replay::from_ron(text)
+}
+
+/// What to enter in the ghost file field of the [`CodeDialog`].
+#[cfg(not(target_arch = "wasm32"))]
+const GHOST_FILE_HINT: &str = "Path of a .replay.ron file (optional)";
+#[cfg(target_arch = "wasm32")]
+const GHOST_FILE_HINT: &str = "Contents of a .replay.ron file (optional)";
+
+/// The prediction of an earlier run of the level, drawn faintly while the level is played again.
+#[derive(Default)]
+struct Ghost {
+    /// The replay of the run, and its level, whose prediction follows the replay.
+    run: Option<(Replay, Level)>,
+    /// Whether the ghost has been looked up for this attempt at the level, see [`update_ghost`].
+    looked_up: bool,
+}
+
+impl Ghost {
+    /// Follows `replay` from now on, if it is a replay of `level`.
+    fn set(&mut self, replay: Replay, level: &Level) -> Result<(), String> {
+        let ghost_level = replay.level.to_level()?;
+        if ghost_level.enemy_path.params() != level.enemy_path.params() {
+            return Err("The ghost is a replay of another level.".to_string());
+        }
+        self.run = Some((replay, ghost_level));
+        Ok(())
+    }
+}
+
+/// The date of the daily challenge that is being played.
+struct DailyChallenge {
+    date: Date,
+}
+
+/// Records the level that is being played, so it can be watched again, see [`record_replay`].
+#[derive(Default)]
+struct ReplayRecorder {
+    replay: Option<Replay>,
+    /// The parameters of the enemy path of the recorded level.
+    enemy_params: Vec<f64>,
+    paused: bool,
+}
+
+impl ReplayRecorder {
+    /// Starts a new recording if `level` isn't the recorded level, or was restarted.
+    fn follow(&mut self, level: &Level) {
+        let enemy_params = level.enemy_path.params();
+        let same_level = self.replay.as_ref().is_some_and(|replay| {
+            self.enemy_params == enemy_params && level.time_taken >= replay.end_time
+        });
+        if !same_level {
+            self.replay = Some(Replay::new(level));
+            self.enemy_params = enemy_params;
+            self.paused = false;
+        }
+        let replay = self.replay.as_mut().unwrap();
+        replay.end_time = level.time_taken;
+        let params = level.player_path.params();
+        if replay.last_prediction() != Some(params.as_slice()) {
+            replay.push(level.time_taken, ReplayEvent::Prediction(params));
+        }
+    }
+
+    /// Records the event before it is applied to `level`.
+    fn record(&mut self, level: &Level, event: ReplayEvent) {
+        self.follow(level);
+        if let Some(replay) = self.replay.as_mut() {
+            replay.push(level.time_taken, event);
+        }
+    }
+
+    /// Ends the recording of a level that is over.
+    fn finish(&mut self, level: &Level, title: String) -> Option<&Replay> {
+        self.follow(level);
+        let replay = self.replay.as_mut()?;
+        replay.title = title;
+        Some(replay)
+    }
+}
+
+/// A replay that is being watched in [`AppState::Replay`].
+struct ReplayPlayer {
+    replay: Replay,
+    /// The time taken in the level at this point of the replay.
+    time: f64,
+    speed: f64,
+    playing: bool,
+    /// The screen to go back to once the replay is closed.
+    return_state: AppState,
+}
+
+impl ReplayPlayer {
+    fn new(replay: Replay, return_state: AppState) -> Self {
+        Self {
+            replay,
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+            return_state,
+        }
+    }
+}
+
+/// The name of the replay of the level file at `path`, e.g. `level1_easy` for `levels/level1_easy.level.ron`.
+fn replay_name(path: &str) -> &str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    file_name.split('.').next().unwrap_or(file_name)
+}
+
+/// The name of the replay of the daily challenge of `date`.
+fn daily_replay_name(date: &str) -> String {
+    format!("daily-{}", date)
+}
+
+/// The highest degree that can be chosen for random levels.
+const MAX_RANDOM_DEGREE: usize = 7;
+
+/// Settings for the "Endless / Random" entry of the level menu.
+struct RandomLevels {
+    degree: usize,
+    difficulty: Difficulty,
+    rng: Rng,
+}
+
+impl Default for RandomLevels {
+    fn default() -> Self {
+        Self {
+            degree: 2,
+            difficulty: Difficulty::Medium,
+            rng: Rng::new(0),
+        }
+    }
+}
+
+impl RandomLevels {
+    fn next_level(&mut self) -> Level {
+        let settings = GeneratorSettings::new(self.degree, self.difficulty);
+        generate_level(&settings, &mut self.rng)
+    }
 }
+
 fn main() {
+    let (profile, profile_storage) = load_profile();
+    #[cfg_attr(not(target_arch = "wasm32"), allow(unused_mut))]
+    let mut initial_state = AppState::MainMenu;
     let mut app = App::build();
     app.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 100.0)))
         .insert_resource(Msaa { samples: 4 })
-        .init_resource::<DebugHelper>()
+        .insert_resource(DebugHelper::from_settings(&profile.settings))
+        .insert_resource(profile)
+        .insert_resource(profile_storage)
         .init_resource::<Level>()
+        .init_resource::<LevelFiles>()
+        .init_resource::<CurrentLevelFile>()
+        .init_resource::<SelectedWorld>()
+        .init_resource::<CodeDialog>()
+        .init_resource::<LevelReports>()
+        .init_resource::<Toast>()
+        .init_resource::<GameMode>()
+        .init_resource::<RandomLevels>()
+        .init_resource::<ReplayRecorder>()
+        .init_resource::<Ghost>()
+        .init_resource::<GameClock>()
+        .insert_resource(SurvivalRun::new(0))
+        .insert_resource(DailyChallenge {
+            date: Date::today(),
+        })
+        .init_resource::<LevelEditor>()
         .add_plugins(DefaultPlugins)
         .add_plugin(EguiPlugin)
-        .add_plugin(AudioPlugin);
+        .add_plugin(AudioPlugin)
+        .add_asset::<LevelDefinition>()
+        .init_asset_loader::<LevelDefinitionLoader>()
+        .add_asset::<LevelIndex>()
+        .init_asset_loader::<LevelIndexLoader>()
+        .add_asset::<Campaign>()
+        .init_asset_loader::<CampaignLoader>();
 
     // when building for Web, use WebGL2 rendering
     #[cfg(target_arch = "wasm32")]
     app.add_plugin(bevy_webgl2::WebGL2Plugin);
 
     // Initial screen
-    app.add_state(AppState::MainMenu)
+    // On the web, a link with a share code opens straight into its level.
+    #[cfg(target_arch = "wasm32")]
+    if let Some(code) = share_code::code_from_page_url() {
+        let mut code_dialog = CodeDialog {
+            open: true,
+            code,
+            ..Default::default()
+        };
+        match code_dialog.level() {
+            Some(level) => {
+                app.insert_resource(level);
+                initial_state = AppState::InGame;
+            }
+            // Show what is wrong with the code.
+            None => initial_state = AppState::LevelMenu,
+        }
+        app.insert_resource(code_dialog);
+    }
+
+    app.add_state(initial_state)
         // Audio
         .add_startup_system(start_background_audio.system())
+        // Levels
+        .add_startup_system(load_level_files.system())
+        .add_system(load_indexed_level_files.system())
+        .add_system(reload_current_level.system())
+        .add_system(update_game_clock.system())
+        .add_system(record_replay.system())
+        .add_system(update_ghost.system())
+        .add_system(update_level_reports.system())
+        // Profile
+        .add_system(record_level_result.system())
+        .add_system(update_settings.system())
+        .add_system(update_unlocked_levels.system())
+        .add_system(update_survival_run.system())
+        .add_system(save_profile.system())
+        .add_system(ui_level_file_errors.system())
         // Always running
         .add_system(update_ui_scale_factor.system())
         .add_system(handle_keys.system())
@@ -46,14 +341,34 @@ fn main() {
         .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(ui_main_menu.system()))
         // Help screen
         .add_system_set(SystemSet::on_update(AppState::About).with_system(ui_about_screen.system()))
+        // World map
+        .add_system_set(SystemSet::on_update(AppState::WorldMap).with_system(ui_world_map.system()))
         // Level menu
         .add_system_set(
             SystemSet::on_update(AppState::LevelMenu).with_system(ui_level_menu.system()),
         )
+        // Level editor
+        .add_system_set(
+            SystemSet::on_update(AppState::LevelEditor).with_system(ui_level_editor.system()),
+        )
         // In Game
-        .add_system_set(SystemSet::on_update(AppState::InGame).with_system(ui_ingame.system()))
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(ui_ingame.system())
+                .with_system(ui_toast.system()),
+        )
         // Paused still has same function, but behaves differently.
-        .add_system_set(SystemSet::on_update(AppState::Paused).with_system(ui_ingame.system()))
+        .add_system_set(
+            SystemSet::on_update(AppState::Paused)
+                .with_system(ui_ingame.system())
+                .with_system(ui_toast.system()),
+        )
+        // Replays are shown like the game, with their own controls.
+        .add_system_set(
+            SystemSet::on_update(AppState::Replay)
+                .with_system(ui_ingame.system())
+                .with_system(ui_replay.system()),
+        )
         .run();
 }
 
@@ -69,14 +384,47 @@ struct DebugHelper {
     color5: [u8; 3],
 }
 
-impl Default for DebugHelper {
-    fn default() -> Self {
+impl DebugHelper {
+    fn from_settings(settings: &Settings) -> Self {
+        let [color1, color2, color3, color4, color5] = settings.colors;
         Self {
-            color1: [46, 86, 126],
-            color2: [66, 92, 121],
-            color3: [74, 119, 157],
-            color4: [85, 91, 106],
-            color5: [59, 59, 74],
+            color1,
+            color2,
+            color3,
+            color4,
+            color5,
+        }
+    }
+
+    fn colors(&self) -> [[u8; 3]; 5] {
+        [
+            self.color1,
+            self.color2,
+            self.color3,
+            self.color4,
+            self.color5,
+        ]
+    }
+}
+
+/// Whether the profile is written back to storage when it changes.
+/// This is turned off if the saved profile couldn't be read, so that it isn't overwritten.
+struct ProfileStorage {
+    can_save: bool,
+}
+
+fn load_profile() -> (Profile, ProfileStorage) {
+    match Profile::load() {
+        Ok(profile) => (
+            profile.unwrap_or_default(),
+            ProfileStorage { can_save: true },
+        ),
+        Err(err) => {
+            eprintln!(
+                "Could not load the profile, progress won't be saved: {}",
+                err
+            );
+            (Profile::default(), ProfileStorage { can_save: false })
         }
     }
 }
@@ -171,10 +519,16 @@ fn ui_set_styles_and_fonts(ctx: &CtxRef, debug_helper: &ResMut<DebugHelper>) {
     ctx.set_style(style);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn ui_main_menu(
     egui_ctx: ResMut<EguiContext>,
     mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
     mut debug_helper: ResMut<DebugHelper>,
+    mut profile: ResMut<Profile>,
+    mut daily_challenge: ResMut<DailyChallenge>,
+    mut current_level_file: ResMut<CurrentLevelFile>,
+    mut game_mode: ResMut<GameMode>,
 ) {
     let ctx = egui_ctx.ctx();
     ui_set_styles_and_fonts(ctx, &debug_helper);
@@ -186,7 +540,7 @@ fn ui_main_menu(
             ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
             let widget_size = size_to_center_widgets(
                 ui.available_size(),
-                egui::vec2(1.0, 3.0),
+                egui::vec2(1.0, 5.0),
                 ui.spacing().item_spacing,
             );
             ui.add_space(widget_size.y);
@@ -195,7 +549,30 @@ fn ui_main_menu(
                 .on_hover_text("Select a level to play.")
                 .clicked()
             {
-                let _ = app_state.set(AppState::LevelMenu);
+                let _ = app_state.set(AppState::WorldMap);
+            }
+            let today = Date::today();
+            let daily_hover_text = match profile.daily_results.get(&today.to_string()) {
+                Some(result) => share_text(today, result),
+                None => format!("Today's level, {}, is the same for everyone.", today),
+            };
+            if ui
+                .add_sized(widget_size, egui::Button::new("Daily Challenge"))
+                .on_hover_text(daily_hover_text)
+                .clicked()
+            {
+                daily_challenge.date = today;
+                commands.insert_resource(today.daily_level());
+                current_level_file.0 = None;
+                *game_mode = GameMode::Daily;
+                let _ = app_state.set(AppState::InGame);
+            }
+            if ui
+                .add_sized(widget_size, egui::Button::new("Level Editor"))
+                .on_hover_text("Make your own levels.")
+                .clicked()
+            {
+                let _ = app_state.set(AppState::LevelEditor);
             }
             if ui
                 .add_sized(widget_size, egui::Button::new("About"))
@@ -227,6 +604,42 @@ fn ui_main_menu(
         ui.label("NonInteractive");
         ui.color_edit_button_srgb(&mut debug_helper.color5);
     });
+
+    egui::Window::new("Settings").show(ctx, |ui| {
+        let mut show_ghost = profile.settings.show_ghost;
+        ui.checkbox(&mut show_ghost, "Show ghost")
+            .on_hover_text("Show the prediction of your best run when you play a level again.");
+        // Only take the profile mutably on a change, so that it isn't saved every frame.
+        if show_ghost != profile.settings.show_ghost {
+            profile.settings.show_ghost = show_ghost;
+        }
+    });
+
+    egui::Window::new("Statistics").show(ctx, |ui| {
+        let statistics = &profile.statistics;
+        egui::Grid::new("Statistics Grid").show(ui, |ui| {
+            ui.label("Levels won");
+            ui.label(format!(
+                "{} / {}",
+                statistics.levels_won, statistics.levels_played
+            ));
+            ui.end_row();
+            ui.label("Wrong confirms");
+            ui.label(statistics.wrong_confirms.to_string());
+            ui.end_row();
+            ui.label("Hints used");
+            ui.label(statistics.hints_used.to_string());
+            ui.end_row();
+            ui.label("Time taken");
+            ui.label(format!("{:.0}s", statistics.time_taken));
+            ui.end_row();
+            if let Some(best) = profile.survival_leaderboard.first() {
+                ui.label("Best survival run");
+                ui.label(format!("{} ({} levels)", best.score, best.levels_cleared));
+                ui.end_row();
+            }
+        });
+    });
 }
 
 fn ui_about_screen(egui_ctx: ResMut<EguiContext>, mut app_state: ResMut<State<AppState>>) {
@@ -254,229 +667,1734 @@ fn ui_about_screen(egui_ctx: ResMut<EguiContext>, mut app_state: ResMut<State<Ap
     });
 }
 
-const LEVELS: (
-    [([f64; 3], f64); 3],
-    [([f64; 5], f64); 3],
-    [([f64; 7], f64); 3],
-) = (
-    // Level 1
-    [
-        ([-1.0, 0.0, 1.0], 100.),
-        ([-1.0, 4.0, 0.0], 75.),
-        ([-3.0, 2.5, 1.5], 50.),
-    ],
-    // Level 2
-    [
-        ([-4.0, 0.0, 0.0, 0.0, 2.0], 150.),
-        ([-4.0, 0.0, 3.5, 0.0, 0.5], 130.),
-        ([-2.0, -2.0, 2.0, 0.0, 1.0], 125.),
-    ],
-    // Level 3
-    [
-        ([-1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0], 200.),
-        ([-2.0, -2.0, 1.0, 0.0, 0.0, 1.0, 0.5], 175.),
-        ([-2.0, -0.5, 1.0, -1.0, 1.0, 2.0, 0.9], 150.),
-    ],
-);
+/// Keeps the level files loaded, so they can be listed in the level menu.
+#[derive(Default)]
+struct LevelFiles {
+    handles: Vec<HandleUntyped>,
+    /// Only used on the web, see [`LevelIndex`].
+    index: Option<Handle<LevelIndex>>,
+    campaign: Handle<Campaign>,
+}
 
-fn ui_level_menu(
-    egui_ctx: ResMut<EguiContext>,
-    mut app_state: ResMut<State<AppState>>,
-    mut commands: Commands,
+/// The world of the campaign whose levels are shown in the level menu,
+/// or `None` for the levels that aren't part of the campaign.
+#[derive(Default)]
+struct SelectedWorld(Option<usize>);
+
+/// The level file that the current [`Level`] was created from, if any.
+#[derive(Default)]
+struct CurrentLevelFile(Option<HandleId>);
+
+/// What is known about a level file, to show in the menus.
+struct LevelReport {
+    estimate: DifficultyEstimate,
+    solvability: SolvabilityReport,
+}
+
+impl LevelReport {
+    fn new(level: &Level) -> Self {
+        Self {
+            estimate: DifficultyEstimate::new(level),
+            solvability: SolvabilityReport::new(level),
+        }
+    }
+}
+
+/// The reports of the loaded level files, or `None` if the level is invalid.
+/// They take a while to compute, so they are only updated when a level file changes.
+#[derive(Default)]
+struct LevelReports(HashMap<HandleId, Option<LevelReport>>);
+
+/// A short message that is shown in game, until `time_shown` is past.
+#[derive(Default)]
+struct Toast {
+    text: String,
+    time_shown: f64,
+}
+
+fn load_level_files(asset_server: Res<AssetServer>, mut level_files: ResMut<LevelFiles>) {
+    // Level designers can edit the level files while the game is running.
+    if let Err(err) = asset_server.watch_for_changes() {
+        eprintln!("Could not watch the level files for changes: {}", err);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    match asset_server.load_folder("levels") {
+        Ok(handles) => level_files.handles = handles,
+        Err(err) => eprintln!("Could not load the levels: {}", err),
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        level_files.index = Some(asset_server.load("levels/levels.index.ron"));
+    }
+    level_files.campaign = asset_server.load("levels/main.campaign.ron");
+}
+
+/// Loads the level files listed in the index once the index itself has been loaded.
+fn load_indexed_level_files(
+    asset_server: Res<AssetServer>,
+    mut level_files: ResMut<LevelFiles>,
+    indices: Res<Assets<LevelIndex>>,
 ) {
-    egui::CentralPanel::default().show(egui_ctx.ctx(), |ui| {
-        ui.vertical_centered(|ui| {
-            if ui
-                .add_sized(ui.available_size() / 8., egui::Button::new("Main Menu"))
-                .clicked()
-            {
-                let _ = app_state.set(AppState::MainMenu);
-            }
-            ui.separator();
-            ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
-            let widget_size = size_to_center_widgets(
-                ui.available_size(),
-                egui::vec2(3.0, 3.0),
-                ui.spacing().item_spacing,
-            );
-            let difficulties = ["Easy", "Medium", "Hard"];
-            egui::Grid::new("Level Grid")
-                .min_col_width(widget_size.x)
-                .min_row_height(widget_size.y)
-                .show(ui, |ui| {
-                    ui.end_row();
-                    for i in 0..3 {
-                        ui.add_space(widget_size.x);
-                        for j in 0..3 {
-                            if ui
-                                .add_sized(
-                                    widget_size,
-                                    egui::Button::new(format!(
-                                        "Level {} {}",
-                                        i + 1,
-                                        difficulties[j]
-                                    )),
-                                )
-                                .clicked()
-                            {
-                                // UGLY!  Don't have time to refactor now.
-                                match i {
-                                    // Unwraps are ok because behaviour has been tested and levels are constant.
-                                    0 => {
-                                        let params = LEVELS.0[j];
-                                        commands.insert_resource(
-                                            Level::new(params.0, params.1).unwrap(),
-                                        );
-                                    }
-                                    1 => {
-                                        let params = LEVELS.1[j];
-                                        commands.insert_resource(
-                                            Level::new(params.0, params.1).unwrap(),
-                                        );
-                                    }
-                                    _ => {
-                                        let params = LEVELS.2[j];
-                                        commands.insert_resource(
-                                            Level::new(params.0, params.1).unwrap(),
-                                        );
-                                    }
-                                };
-                                let _ = app_state.set(AppState::InGame);
-                            }
-                        }
-                        ui.end_row();
-                    }
-                });
-        });
-    });
+    let index = match level_files.index.as_ref().and_then(|h| indices.get(h)) {
+        Some(index) => index,
+        None => return,
+    };
+    level_files.handles = index
+        .0
+        .iter()
+        .map(|path| asset_server.load_untyped(path.as_str()))
+        .collect();
+    level_files.index = None;
 }
 
-fn ui_ingame(
-    egui_ctx: ResMut<EguiContext>,
+/// Rebuilds the current level when its level file has been edited.
+fn reload_current_level(
+    mut events: EventReader<AssetEvent<LevelDefinition>>,
+    definitions: Res<Assets<LevelDefinition>>,
+    current_level_file: Res<CurrentLevelFile>,
     mut level: ResMut<Level>,
-    mut app_state: ResMut<State<AppState>>,
+    mut toast: ResMut<Toast>,
     time: Res<Time>,
 ) {
-    let ctx = egui_ctx.ctx();
-    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-        // The top panel is often a good place for a menu bar:
-        egui::menu::bar(ui, |ui| {
-            egui::menu::menu(ui, "Options", |ui| {
-                if ui.button("Main Menu").clicked() {
-                    let _ = app_state.set(AppState::MainMenu);
-                }
-                if ui.button("Quit").clicked() {
-                    std::process::exit(0);
-                }
-            });
-        });
-    });
-
-    let available_rect = ctx.available_rect();
-    let available_width = available_rect.width();
-    let available_height = available_rect.height();
-
-    let playing = app_state.current() == &AppState::InGame && !level.won && !level.lost;
-    // Game is displayed here.
-    egui::CentralPanel::default().show(ctx, |ui| {
-        ui.set_enabled(playing);
-        if playing {
-            level.time_taken += time.delta_seconds_f64();
-            if level.time_taken >= level.max_time {
-                level.lost = true;
-            }
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        if current_level_file.0 != Some(handle.id) {
+            continue;
         }
-        ui.label(format!(
-            "Time left: {:.2}s",
-            level.max_time - level.time_taken
-        ));
-        ui.add(egui::widgets::ProgressBar::new(
-            (level.time_taken / level.max_time).clamp(0.0, 1.0) as f32,
-        ));
-        // Draw the background even when paused
-
-        // Calculate the paths for the player and enemy
-        let enemy_path = Line::new(Values::from_values_iter(
-            level
-                .domain_range_time(0.01)
-                .map(|x| Value::new(x, level.eval_enemy_poly(x))),
-        ))
-        .name("Enemy Path")
-        .color(Color32::RED)
-        .width(2.5);
-
-        let player_path = Points::new(Values::from_values_iter(
-            level
-                // Bigger spacing because it's just points.
-                .domain_range_limits(0.025)
-                .map(|x| Value::new(x, level.eval_player_poly(x))),
-        ))
-        .name("Prediction")
-        .color(Color32::GREEN)
-        .radius(2.5);
-
-        let mut plot = Plot::new("rocket_paths")
-            .line(enemy_path)
-            .points(player_path)
-            .allow_drag(false)
-            .legend(Legend {
-                background_alpha: 0.5,
-                ..Default::default()
-            });
-        for limit in level.limits {
-            plot = plot.include_x(limit.x);
-            plot = plot.include_y(limit.y);
+        let definition = match definitions.get(handle) {
+            Some(definition) => definition,
+            None => continue,
+        };
+        // Invalid levels are reported by the loader, so the old level is simply kept.
+        if let Ok(new_level) = definition.to_level() {
+            *level = level.carry_over(new_level);
+            *toast = Toast {
+                text: format!("Reloaded level '{}'.", definition.title),
+                time_shown: time.seconds_since_startup() + 3.0,
+            };
         }
+    }
+}
 
-        ui.add(plot);
+/// Lets game time pass in the level, in steps of [`TIME_STEP`].
+fn update_game_clock(
+    time: Res<Time>,
+    app_state: Res<State<AppState>>,
+    game_mode: Res<GameMode>,
+    mut clock: ResMut<GameClock>,
+    mut level: ResMut<Level>,
+) {
+    // The level only plays on, out of the pause menu, the other menus and replays.
+    let paused = app_state.current() != &AppState::InGame;
+    if clock.paused != paused {
+        clock.paused = paused;
+    }
+    if game_mode.is_ranked() && clock.speed() != 1.0 {
+        clock.set_speed(1.0);
+    }
+    let steps = clock.tick(time.delta_seconds_f64());
+    for _ in 0..steps {
+        if level.won || level.lost {
+            break;
+        }
+        level.advance_time(TIME_STEP);
+    }
+}
 
-        ctx.request_repaint();
-    });
+/// Adds every level to the profile once it is over, and saves its replay if it was a new best.
+#[allow(clippy::too_many_arguments)]
+fn record_level_result(
+    level: Res<Level>,
+    app_state: Res<State<AppState>>,
+    game_mode: Res<GameMode>,
+    daily_challenge: Res<DailyChallenge>,
+    current_level_file: Res<CurrentLevelFile>,
+    asset_server: Res<AssetServer>,
+    definitions: Res<Assets<LevelDefinition>>,
+    mut profile: ResMut<Profile>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut recorded: Local<bool>,
+) {
+    // The levels of a replay were recorded when they were played.
+    if app_state.current() == &AppState::Replay {
+        *recorded = true;
+        return;
+    }
+    if !level.won && !level.lost {
+        *recorded = false;
+        return;
+    }
+    if *recorded {
+        return;
+    }
+    *recorded = true;
+    let date = daily_challenge.date.to_string();
+    let title = match *game_mode {
+        GameMode::Daily => format!("Daily Challenge {}", date),
+        GameMode::Random => "Random Level".to_string(),
+        GameMode::Survival => "Survival Level".to_string(),
+        GameMode::Levels | GameMode::PlayTest => current_level_file
+            .0
+            .and_then(|id| definitions.get(id))
+            .map_or_else(|| "Custom Level".to_string(), |d| d.title.clone()),
+    };
+    let replay = recorder.finish(&level, title);
+    // Only level files have a best result, random levels are different every time.
+    let path = current_level_file
+        .0
+        .filter(|_| *game_mode == GameMode::Levels)
+        .and_then(|id| asset_server.get_handle_path(id))
+        .map(|path| path.path().to_string_lossy().into_owned());
+    let mut save_as = None;
+    if profile.record(&level, path.as_deref()) {
+        save_as = path.as_deref().map(|path| replay_name(path).to_string());
+    }
+    if *game_mode == GameMode::Daily && profile.record_daily(&level, daily_challenge.date) {
+        save_as = Some(daily_replay_name(&date));
+    }
+    if let (Some(replay), Some(name)) = (replay, save_as) {
+        if let Err(err) = replay.save(&name) {
+            eprintln!("Could not save the replay: {}", err);
+        }
+    }
+}
 
-    // Control window
-    let mut frame = Frame::window(&ctx.style());
-    frame.fill =
-        Color32::from_rgba_premultiplied(frame.fill.r(), frame.fill.g(), frame.fill.b(), 100);
-    egui::Window::new("Controls")
-        .frame(frame)
-        .default_pos(egui::pos2(40., 100.))
-        .show(ctx, |ui| {
-            ui.set_enabled(playing);
-            ui.label("Change the path to match that of your enemy using the controls.");
-            let mut equation = String::from("");
-            for i in 0..level.enemy_coefs.len() {
-                equation += &char::from_u32(97 + i as u32).unwrap().to_string();
-                match level.enemy_coefs.len() - 1 - i {
-                    0 => continue,
-                    1 => equation += "x + ",
-                    n => equation += &format!("x^{} + ", n),
+/// Looks up the ghost of every attempt at a level, and moves it along with the time taken.
+#[allow(clippy::too_many_arguments)]
+fn update_ghost(
+    level: Res<Level>,
+    app_state: Res<State<AppState>>,
+    game_mode: Res<GameMode>,
+    daily_challenge: Res<DailyChallenge>,
+    current_level_file: Res<CurrentLevelFile>,
+    asset_server: Res<AssetServer>,
+    mut ghost: ResMut<Ghost>,
+) {
+    let playing = matches!(app_state.current(), AppState::InGame | AppState::Paused);
+    if !playing || level.won || level.lost {
+        // The next attempt looks it up again, as the best run may have changed.
+        ghost.looked_up = false;
+        return;
+    }
+    if !ghost.looked_up {
+        ghost.looked_up = true;
+        let name = match *game_mode {
+            GameMode::Levels => current_level_file
+                .0
+                .and_then(|id| asset_server.get_handle_path(id))
+                .map(|path| replay_name(&path.path().to_string_lossy()).to_string()),
+            GameMode::Daily => Some(daily_replay_name(&daily_challenge.date.to_string())),
+            GameMode::PlayTest | GameMode::Random | GameMode::Survival => None,
+        };
+        match name.map(|name| Replay::load(&name)).transpose() {
+            Ok(Some(Some(replay))) => {
+                if let Err(err) = ghost.set(replay, &level) {
+                    ghost.run = None;
+                    eprintln!("Could not show the ghost: {}", err);
                 }
             }
-            ui.label(format!("Path: {}", equation));
-            for i in 0..level.enemy_coefs.len() {
-                ui.add(
-                    egui::DragValue::new(&mut level.player_coefs[i])
-                        .clamp_range(-10.0..=10.0)
-                        .speed(0.1)
-                        .prefix(format!("{}: ", char::from_u32(97 + i as u32).unwrap())),
-                );
-            }
-            if ui
-                .button("Confirm")
-                .on_hover_text("Confirm path prediction.")
-                .on_hover_text("Incorrect prediction will result in a time penalty.")
-                .clicked()
-            {
-                if !level.check_won() {
-                    level.time_taken += 1.0;
+            Ok(_) => {
+                // An imported ghost is kept for the level that it was imported with.
+                let other_level = ghost.run.as_ref().is_some_and(|(_, ghost_level)| {
+                    ghost_level.enemy_path.params() != level.enemy_path.params()
+                });
+                if other_level {
+                    ghost.run = None;
                 }
             }
-        });
-
-    // Pause Window
-    let mut frame = Frame::window(&ctx.style());
-    frame.margin = egui::vec2(50., 20.);
-    frame.fill =
+            Err(err) => {
+                ghost.run = None;
+                eprintln!("Could not load the ghost: {}", err);
+            }
+        }
+    }
+    if let Some((replay, ghost_level)) = ghost.run.as_mut() {
+        if let Some(params) = replay.prediction_at(level.time_taken) {
+            ghost_level.player_path.set_params(params);
+        }
+    }
+}
+
+/// Follows the level that is being played with the [`ReplayRecorder`].
+fn record_replay(
+    level: Res<Level>,
+    app_state: Res<State<AppState>>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let paused = match app_state.current() {
+        AppState::InGame => false,
+        AppState::Paused => true,
+        _ => return,
+    };
+    // The end of a level is recorded by `record_level_result`.
+    if level.won || level.lost {
+        return;
+    }
+    recorder.follow(&level);
+    if recorder.paused != paused {
+        recorder.paused = paused;
+        let event = if paused {
+            ReplayEvent::Pause
+        } else {
+            ReplayEvent::Resume
+        };
+        recorder.record(&level, event);
+    }
+}
+
+/// Copies the colours that were edited in the main menu to the profile.
+fn update_settings(debug_helper: Res<DebugHelper>, mut profile: ResMut<Profile>) {
+    let colors = debug_helper.colors();
+    // Only take the profile mutably on a change, so that it isn't saved every frame.
+    if profile.settings.colors != colors {
+        profile.settings.colors = colors;
+    }
+}
+
+/// Keeps the levels that the campaign unlocked in the profile,
+/// so they stay unlocked when the campaign changes.
+fn update_unlocked_levels(
+    level_files: Res<LevelFiles>,
+    campaigns: Res<Assets<Campaign>>,
+    mut profile: ResMut<Profile>,
+) {
+    let campaign = match campaigns.get(&level_files.campaign) {
+        Some(campaign) => campaign,
+        None => return,
+    };
+    let progress = campaign.progress(&profile.best_results, &profile.unlocked_levels);
+    let new_files = progress
+        .unlocked_files(campaign)
+        .filter(|file| !profile.unlocked_levels.contains(*file))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if !new_files.is_empty() {
+        profile.unlocked_levels.extend(new_files);
+    }
+}
+
+/// Adds every level of survival mode to the run once it is over,
+/// and the run to the leaderboard once it is lost.
+fn update_survival_run(
+    level: Res<Level>,
+    app_state: Res<State<AppState>>,
+    game_mode: Res<GameMode>,
+    mut run: ResMut<SurvivalRun>,
+    mut profile: ResMut<Profile>,
+    mut recorded: Local<bool>,
+) {
+    if *game_mode != GameMode::Survival {
+        return;
+    }
+    if app_state.current() == &AppState::Replay {
+        *recorded = true;
+        return;
+    }
+    if !level.won && !level.lost {
+        *recorded = false;
+        return;
+    }
+    if *recorded {
+        return;
+    }
+    *recorded = true;
+    run.finish_level(&level);
+    if run.over {
+        run.leaderboard_place = add_to_leaderboard(&mut profile.survival_leaderboard, run.record());
+    }
+}
+
+/// Starts a new survival run, with its first level.
+fn start_survival_run(
+    run: &mut SurvivalRun,
+    random_levels: &mut RandomLevels,
+    time: &Time,
+) -> Level {
+    // Mix in the time, so every run gets different levels.
+    let seed = random_levels.rng.next_u64() ^ time.seconds_since_startup().to_bits();
+    *run = SurvivalRun::new(seed);
+    run.next_level()
+}
+
+fn save_profile(profile: Res<Profile>, profile_storage: Res<ProfileStorage>) {
+    if profile.is_changed() && profile_storage.can_save {
+        if let Err(err) = profile.save() {
+            eprintln!("Could not save the profile: {}", err);
+        }
+    }
+}
+
+fn update_level_reports(
+    mut events: EventReader<AssetEvent<LevelDefinition>>,
+    definitions: Res<Assets<LevelDefinition>>,
+    mut reports: ResMut<LevelReports>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if let Some(definition) = definitions.get(handle) {
+                    let report = definition
+                        .to_level()
+                        .ok()
+                        .map(|level| LevelReport::new(&level));
+                    reports.0.insert(handle.id, report);
+                }
+            }
+            AssetEvent::Removed { handle } => {
+                reports.0.remove(&handle.id);
+            }
+        }
+    }
+}
+
+fn ui_toast(egui_ctx: ResMut<EguiContext>, toast: Res<Toast>, time: Res<Time>) {
+    if time.seconds_since_startup() > toast.time_shown {
+        return;
+    }
+    egui::Area::new("Toast")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -40.0))
+        .show(egui_ctx.ctx(), |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(&toast.text);
+            });
+        });
+}
+
+/// Shows the problems with the level files, so they can be fixed without restarting the game.
+fn ui_level_file_errors(egui_ctx: ResMut<EguiContext>, errors: Res<LevelFileErrors>) {
+    let file_errors = errors.get();
+    if file_errors.is_empty() {
+        return;
+    }
+    egui::Window::new("Level File Errors")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .show(egui_ctx.ctx(), |ui| {
+            for (path, error) in file_errors {
+                ui.label(path.display().to_string());
+                ui.colored_label(Color32::RED, error);
+                ui.separator();
+            }
+            if ui.button("Dismiss").clicked() {
+                errors.clear();
+            }
+        });
+}
+
+/// A level file as shown in the level menu.
+struct LevelEntry<'a> {
+    id: HandleId,
+    /// The asset path of the level file.
+    path: Option<String>,
+    definition: &'a LevelDefinition,
+    /// `None` if the level is invalid, or hasn't been checked yet.
+    report: Option<&'a LevelReport>,
+}
+
+impl<'a> LevelEntry<'a> {
+    fn new(
+        id: HandleId,
+        definition: &'a LevelDefinition,
+        asset_server: &AssetServer,
+        reports: &'a LevelReports,
+    ) -> Self {
+        Self {
+            id,
+            path: asset_server
+                .get_handle_path(id)
+                .map(|path| path.path().to_string_lossy().into_owned()),
+            definition,
+            report: reports.0.get(&id).and_then(Option::as_ref),
+        }
+    }
+
+    /// Invalid levels are sorted after all the others.
+    fn score(&self) -> f64 {
+        self.report
+            .map_or(f64::INFINITY, |report| report.estimate.score)
+    }
+}
+
+/// Groups the level files by pack, from the easiest to the hardest.
+/// Packs are sorted by the average difficulty of their levels.
+fn level_packs<'a>(
+    asset_server: &AssetServer,
+    definitions: &'a Assets<LevelDefinition>,
+    reports: &'a LevelReports,
+) -> Vec<(&'a str, Vec<LevelEntry<'a>>)> {
+    let mut levels = definitions
+        .iter()
+        .map(|(id, definition)| LevelEntry::new(id, definition, asset_server, reports))
+        .collect::<Vec<_>>();
+    levels.sort_by(|a, b| {
+        a.definition
+            .pack
+            .cmp(&b.definition.pack)
+            .then_with(|| a.score().total_cmp(&b.score()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    let mut packs: Vec<(&str, Vec<LevelEntry>)> = Vec::new();
+    for entry in levels {
+        match packs.last_mut() {
+            Some((pack, pack_levels)) if *pack == entry.definition.pack => pack_levels.push(entry),
+            _ => packs.push((&entry.definition.pack, vec![entry])),
+        }
+    }
+    let average_score = |levels: &[LevelEntry]| {
+        levels.iter().map(LevelEntry::score).sum::<f64>() / levels.len() as f64
+    };
+    // Scores can be infinite, and the average of infinities of both signs is NaN,
+    // which `total_cmp` still puts in a consistent place.
+    packs.sort_by(|(a_pack, a), (b_pack, b)| {
+        average_score(a)
+            .total_cmp(&average_score(b))
+            .then_with(|| a_pack.cmp(b_pack))
+    });
+    packs
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ui_world_map(
+    egui_ctx: ResMut<EguiContext>,
+    mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    level_files: Res<LevelFiles>,
+    campaigns: Res<Assets<Campaign>>,
+    definitions: Res<Assets<LevelDefinition>>,
+    profile: Res<Profile>,
+    mut selected_world: ResMut<SelectedWorld>,
+    mut current_level_file: ResMut<CurrentLevelFile>,
+    mut game_mode: ResMut<GameMode>,
+    mut random_levels: ResMut<RandomLevels>,
+    mut survival_run: ResMut<SurvivalRun>,
+    time: Res<Time>,
+) {
+    egui::CentralPanel::default().show(egui_ctx.ctx(), |ui| {
+        ui.vertical_centered(|ui| {
+            if ui
+                .add_sized(ui.available_size() / 8., egui::Button::new("Main Menu"))
+                .clicked()
+            {
+                let _ = app_state.set(AppState::MainMenu);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Survival")
+                    .on_hover_text("Missiles keep coming until one hits the town.")
+                    .on_hover_text("The time left of a level carries over to the next one.")
+                    .clicked()
+                {
+                    let level = start_survival_run(&mut survival_run, &mut random_levels, &time);
+                    commands.insert_resource(level);
+                    current_level_file.0 = None;
+                    *game_mode = GameMode::Survival;
+                    let _ = app_state.set(AppState::InGame);
+                }
+                if ui
+                    .button("Random")
+                    .on_hover_text("Play randomly generated levels.")
+                    .clicked()
+                {
+                    // Mix in the time, so every session gets different levels.
+                    let seed =
+                        random_levels.rng.next_u64() ^ time.seconds_since_startup().to_bits();
+                    random_levels.rng = Rng::new(seed);
+                    commands.insert_resource(random_levels.next_level());
+                    current_level_file.0 = None;
+                    *game_mode = GameMode::Random;
+                    let _ = app_state.set(AppState::InGame);
+                }
+                ui.add(
+                    egui::DragValue::new(&mut random_levels.degree)
+                        .clamp_range(2..=MAX_RANDOM_DEGREE)
+                        .prefix("Degree: "),
+                );
+                for difficulty in Difficulty::ALL {
+                    ui.selectable_value(
+                        &mut random_levels.difficulty,
+                        difficulty,
+                        difficulty.to_string(),
+                    );
+                }
+            });
+            ui.separator();
+            let campaign = match campaigns.get(&level_files.campaign) {
+                Some(campaign) => campaign,
+                None => {
+                    match asset_server.get_load_state(&level_files.campaign) {
+                        LoadState::Failed => ui.label(
+                            "Could not load the campaign in 'assets/levels/main.campaign.ron'.",
+                        ),
+                        _ => ui.label("Loading the campaign..."),
+                    };
+                    return;
+                }
+            };
+            let progress = campaign.progress(&profile.best_results, &profile.unlocked_levels);
+            ui.heading(format!("★ {}", progress.stars));
+            // Level files that aren't in the campaign, like the ones made in the level editor.
+            let campaign_files = campaign.files().collect::<HashSet<_>>();
+            let has_other_levels = definitions.iter().any(|(id, _)| {
+                asset_server.get_handle_path(id).is_some_and(|path| {
+                    !campaign_files.contains(path.path().to_string_lossy().as_ref())
+                })
+            });
+
+            let num_buttons = campaign.worlds.len() + has_other_levels as usize;
+            ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
+            let widget_size = size_to_center_widgets(
+                ui.available_size(),
+                egui::vec2(num_buttons as f32, 1.0),
+                ui.spacing().item_spacing,
+            );
+            ui.horizontal(|ui| {
+                ui.add_space(widget_size.x);
+                for (i, (world, world_progress)) in
+                    campaign.worlds.iter().zip(&progress.worlds).enumerate()
+                {
+                    let text = if world_progress.unlocked {
+                        format!(
+                            "{}\n{}/{} won\n★ {}",
+                            world.name,
+                            world_progress.levels_won,
+                            world.levels.len(),
+                            world_progress.stars
+                        )
+                    } else {
+                        format!("{}\nLocked", world.name)
+                    };
+                    let hover_text = if world_progress.unlocked {
+                        world.description.clone()
+                    } else {
+                        format!("{}\nTo unlock: {}", world.description, world.unlock)
+                    };
+                    if ui
+                        .add_sized(
+                            widget_size,
+                            egui::Button::new(text).enabled(world_progress.unlocked),
+                        )
+                        .on_hover_text(hover_text)
+                        .clicked()
+                    {
+                        selected_world.0 = Some(i);
+                        let _ = app_state.set(AppState::LevelMenu);
+                    }
+                }
+                if has_other_levels
+                    && ui
+                        .add_sized(widget_size, egui::Button::new("Other Levels"))
+                        .on_hover_text("Levels that aren't part of the campaign.")
+                        .clicked()
+                {
+                    selected_world.0 = None;
+                    let _ = app_state.set(AppState::LevelMenu);
+                }
+            });
+        });
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ui_level_menu(
+    egui_ctx: ResMut<EguiContext>,
+    mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    level_files: Res<LevelFiles>,
+    campaigns: Res<Assets<Campaign>>,
+    definitions: Res<Assets<LevelDefinition>>,
+    reports: Res<LevelReports>,
+    profile: Res<Profile>,
+    selected_world: Res<SelectedWorld>,
+    mut code_dialog: ResMut<CodeDialog>,
+    mut current_level_file: ResMut<CurrentLevelFile>,
+    mut game_mode: ResMut<GameMode>,
+    mut ghost: ResMut<Ghost>,
+    mut error: Local<Option<String>>,
+    mut replays_open: Local<bool>,
+) {
+    let mut dialog_open = code_dialog.open;
+    egui::Window::new("Enter Code")
+        .open(&mut dialog_open)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_ctx.ctx(), |ui| {
+            ui.label("Paste the code or the link of a shared level.");
+            ui.add(
+                egui::TextEdit::singleline(&mut code_dialog.code)
+                    .hint_text("Share code")
+                    .desired_width(400.0),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut code_dialog.ghost_file)
+                    .hint_text(GHOST_FILE_HINT)
+                    .desired_width(400.0),
+            )
+            .on_hover_text("The replay of someone else's run, to play against its prediction.");
+            if let Some(error) = code_dialog.error.as_ref() {
+                ui.colored_label(Color32::RED, error);
+            }
+            if ui.button("Play").clicked() {
+                if let Some(level) = code_dialog.level() {
+                    ghost.run = None;
+                    let ghost_file = code_dialog.ghost_file.trim();
+                    let ghost_result = if ghost_file.is_empty() {
+                        Ok(())
+                    } else {
+                        read_ghost_file(ghost_file).and_then(|replay| ghost.set(replay, &level))
+                    };
+                    match ghost_result {
+                        Ok(()) => {
+                            commands.insert_resource(level);
+                            current_level_file.0 = None;
+                            *game_mode = GameMode::Levels;
+                            code_dialog.open = false;
+                            let _ = app_state.set(AppState::InGame);
+                        }
+                        Err(err) => code_dialog.error = Some(err),
+                    }
+                }
+            }
+        });
+    // Closing the window with its cross button.
+    code_dialog.open &= dialog_open;
+
+    // Replays are saved for the best result of each level and daily challenge.
+    let level_replays = profile.best_results.iter().map(|(path, result)| {
+        let id = asset_server
+            .get_handle::<LevelDefinition, _>(path.as_str())
+            .id;
+        let title = definitions
+            .get(id)
+            .map_or_else(|| path.clone(), |definition| definition.title.clone());
+        (title, replay_name(path).to_string(), result)
+    });
+    let daily_replays = profile.daily_results.iter().rev().map(|(date, result)| {
+        let title = format!("Daily Challenge {}", date);
+        (title, daily_replay_name(date), result)
+    });
+    let replays = level_replays.chain(daily_replays).collect::<Vec<_>>();
+    egui::Window::new("Replays")
+        .open(&mut replays_open)
+        .collapsible(false)
+        .scroll(true)
+        .show(egui_ctx.ctx(), |ui| {
+            if replays.is_empty() {
+                ui.label("The replay of your best result of each level is saved here.");
+            }
+            egui::Grid::new("Replay List").show(ui, |ui| {
+                for (title, name, result) in &replays {
+                    ui.label(title);
+                    if result.won {
+                        ui.label(format!("{} {}", star_text(result.stars), result.score));
+                    } else {
+                        ui.label("Lost");
+                    }
+                    if ui.button("Watch").clicked() {
+                        match Replay::load(name) {
+                            Ok(Some(replay)) => {
+                                commands.insert_resource(ReplayPlayer::new(
+                                    replay,
+                                    AppState::LevelMenu,
+                                ));
+                                *error = None;
+                                let _ = app_state.set(AppState::Replay);
+                            }
+                            Ok(None) => {
+                                *error = Some(format!("There is no replay of '{}'.", title))
+                            }
+                            Err(err) => *error = Some(err),
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    egui::CentralPanel::default().show(egui_ctx.ctx(), |ui| {
+        ui.vertical_centered(|ui| {
+            if ui
+                .add_sized(ui.available_size() / 8., egui::Button::new("World Map"))
+                .clicked()
+            {
+                let _ = app_state.set(AppState::WorldMap);
+            }
+            if ui
+                .button("Enter Code")
+                .on_hover_text("Play a level that someone shared with you.")
+                .clicked()
+            {
+                code_dialog.open = true;
+                code_dialog.error = None;
+            }
+            if ui
+                .button("Replays")
+                .on_hover_text("Watch how your best results were played.")
+                .clicked()
+            {
+                *replays_open = true;
+            }
+            ui.separator();
+            if let Some(error) = error.as_ref() {
+                ui.colored_label(Color32::RED, error);
+            }
+            let campaign = campaigns.get(&level_files.campaign);
+            // The rows of the grid, with whether each level is unlocked.
+            let packs = match (campaign, selected_world.0) {
+                (Some(campaign), Some(world)) => {
+                    let world_progress = campaign
+                        .progress(&profile.best_results, &profile.unlocked_levels)
+                        .worlds
+                        .swap_remove(world);
+                    let world = &campaign.worlds[world];
+                    ui.heading(&world.name);
+                    ui.label(&world.description);
+                    let mut levels = Vec::new();
+                    for (level, unlocked) in world.levels.iter().zip(world_progress.levels_unlocked)
+                    {
+                        let id = asset_server
+                            .get_handle::<LevelDefinition, _>(level.file.as_str())
+                            .id;
+                        match definitions.get(id) {
+                            Some(definition) => levels.push((
+                                LevelEntry::new(id, definition, &asset_server, &reports),
+                                (!unlocked).then_some(level.unlock),
+                            )),
+                            None => {
+                                ui.colored_label(
+                                    Color32::RED,
+                                    format!("Level file '{}' is missing.", level.file),
+                                );
+                            }
+                        }
+                    }
+                    vec![("", levels)]
+                }
+                (campaign, _) => {
+                    let campaign_files = campaign
+                        .map(|campaign| campaign.files().collect::<HashSet<_>>())
+                        .unwrap_or_default();
+                    level_packs(&asset_server, &definitions, &reports)
+                        .into_iter()
+                        .map(|(pack, levels)| {
+                            let levels = levels
+                                .into_iter()
+                                .filter(|entry| {
+                                    entry
+                                        .path
+                                        .as_deref()
+                                        .is_none_or(|path| !campaign_files.contains(path))
+                                })
+                                .map(|entry| (entry, None))
+                                .collect::<Vec<_>>();
+                            (pack, levels)
+                        })
+                        .filter(|(_, levels)| !levels.is_empty())
+                        .collect()
+                }
+            };
+            if packs.iter().all(|(_, levels)| levels.is_empty()) {
+                let load_state =
+                    asset_server.get_group_load_state(level_files.handles.iter().map(|h| h.id));
+                if level_files.index.is_some() || load_state == LoadState::Loading {
+                    ui.label("Loading levels...");
+                } else {
+                    ui.label("No levels found in the 'assets/levels' folder.");
+                }
+                return;
+            }
+            let num_columns = packs.iter().map(|(_, levels)| levels.len()).max().unwrap();
+            ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
+            let widget_size = size_to_center_widgets(
+                ui.available_size(),
+                egui::vec2(num_columns as f32, packs.len() as f32),
+                ui.spacing().item_spacing,
+            );
+            egui::Grid::new("Level Grid")
+                .min_col_width(widget_size.x)
+                .min_row_height(widget_size.y)
+                .show(ui, |ui| {
+                    ui.end_row();
+                    for (pack, levels) in packs {
+                        ui.add_sized(widget_size, egui::Label::new(pack));
+                        for (entry, lock) in levels {
+                            if !level_button(ui, widget_size, &entry, &profile, lock) {
+                                continue;
+                            }
+                            match entry.definition.to_level() {
+                                Ok(level) => {
+                                    commands.insert_resource(level);
+                                    current_level_file.0 = Some(entry.id);
+                                    *game_mode = GameMode::Levels;
+                                    *error = None;
+                                    let _ = app_state.set(AppState::InGame);
+                                }
+                                Err(err) => {
+                                    *error = Some(format!(
+                                        "Level '{}' is invalid: {}",
+                                        entry.definition.title, err
+                                    ))
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    });
+}
+
+/// Shows a button for the level in the level menu, and returns whether it was clicked.
+/// A level with a `lock` is disabled, and shows what it takes to unlock it.
+fn level_button(
+    ui: &mut egui::Ui,
+    size: egui::Vec2,
+    entry: &LevelEntry,
+    profile: &Profile,
+    lock: Option<Unlock>,
+) -> bool {
+    let definition = entry.definition;
+    let mut text = format!("{}\n{}", definition.title, definition.difficulty);
+    let mut hover_text = match entry.report {
+        Some(report) => {
+            text += &format!(" ({:.1})", report.estimate.score);
+            if !report.solvability.is_fair() {
+                text += ", unfair";
+            }
+            format!(
+                "{}\n{}",
+                difficulty_breakdown(&report.estimate),
+                solvability_summary(&report.solvability, definition.max_time)
+            )
+        }
+        None => "This level is invalid.".to_string(),
+    };
+    let best = entry
+        .path
+        .as_ref()
+        .and_then(|path| profile.best_results.get(path));
+    if let Some(best) = best {
+        text += &format!("\n{} {}", star_text(best.stars), best.score);
+    }
+    if let Some(lock) = lock {
+        text += "\nLocked";
+        hover_text = format!("To unlock: {}", lock);
+    }
+    ui.add_sized(size, egui::Button::new(text).enabled(lock.is_none()))
+        .on_hover_text(hover_text)
+        .clicked()
+}
+
+/// Shows the result of a level of survival mode.
+/// After a win the next level follows, after a loss the run is over and its summary is shown.
+fn ui_survival_transition(
+    ctx: &CtxRef,
+    level: &mut Level,
+    app_state: &mut State<AppState>,
+    run: &mut SurvivalRun,
+    random_levels: &mut RandomLevels,
+    profile: &Profile,
+    time: &Time,
+) {
+    let available_rect = ctx.available_rect();
+    let mut frame = Frame::window(&ctx.style());
+    frame.margin = egui::vec2(50., 20.);
+    frame.fill = if level.won {
+        Color32::from_rgba_premultiplied(20, 80, 30, 150)
+    } else {
+        Color32::from_rgba_premultiplied(90, 30, 20, 150)
+    };
+    egui::Window::new("Survival")
+        .frame(frame)
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                if level.won {
+                    ui.heading(format!("Level {} cleared!", run.levels_cleared));
+                    ui.label(format!("+{} points", LevelResult::new(level).score));
+                    ui.label(format!("{:.1}s carries over", run.time_carried));
+                    let next = run.settings();
+                    ui.label(format!(
+                        "Next: degree {}, {:.0}s",
+                        next.degree,
+                        next.max_time + run.time_carried
+                    ));
+                } else {
+                    ui.heading("The run is over!");
+                    ui.label(format!("Levels cleared: {}", run.levels_cleared));
+                    ui.label(format!("Total score: {}", run.score));
+                    match run.leaderboard_place {
+                        Some(place) => ui.label(format!("#{} on the leaderboard", place + 1)),
+                        None => ui.label("Not on the leaderboard"),
+                    };
+                    ui.separator();
+                    ui.label("Leaderboard");
+                    egui::Grid::new("Survival Leaderboard").show(ui, |ui| {
+                        for (i, record) in profile.survival_leaderboard.iter().enumerate() {
+                            let text_color = if Some(i) == run.leaderboard_place {
+                                Color32::YELLOW
+                            } else {
+                                Color32::LIGHT_GRAY
+                            };
+                            ui.colored_label(text_color, format!("#{}", i + 1));
+                            ui.colored_label(text_color, record.score.to_string());
+                            ui.colored_label(
+                                text_color,
+                                format!(
+                                    "{} levels, degree {}",
+                                    record.levels_cleared, record.degree
+                                ),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+            ui.vertical(|ui| {
+                ui.add_space(20.);
+                ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
+                let widget_size = size_to_center_widgets(
+                    available_rect.size(),
+                    egui::vec2(1.0, 5.0),
+                    ui.spacing().item_spacing,
+                );
+                if level.won {
+                    if ui
+                        .add_sized(widget_size, egui::Button::new("Next Level"))
+                        .on_hover_text("The next missile is on its way.")
+                        .clicked()
+                    {
+                        *level = run.next_level();
+                    }
+                } else if ui
+                    .add_sized(widget_size, egui::Button::new("New Run"))
+                    .on_hover_text("Start survival mode over.")
+                    .clicked()
+                {
+                    *level = start_survival_run(run, random_levels, time);
+                }
+                if ui
+                    .add_sized(widget_size, egui::Button::new("World Map"))
+                    .clicked()
+                {
+                    let _ = app_state.set(AppState::WorldMap);
+                }
+                if ui
+                    .add_sized(widget_size, egui::Button::new("Main Menu"))
+                    .clicked()
+                {
+                    let _ = app_state.set(AppState::MainMenu);
+                }
+            });
+        });
+}
+
+/// Describes the factors of the difficulty score, for a tooltip.
+fn difficulty_breakdown(estimate: &DifficultyEstimate) -> String {
+    format!(
+        "Difficulty score: {:.1}\n\
+         Degree: {}\n\
+         Average coefficient: {:.2}\n\
+         Revealed per second: {:.1}%\n\
+         Closest roots: {:.2} apart\n\
+         Ambiguity: 10^{:.1} paths fit the first half",
+        estimate.score,
+        estimate.degree,
+        estimate.coefficient_magnitude,
+        estimate.reveal_rate,
+        estimate.min_root_gap,
+        estimate.ambiguity,
+    )
+}
+
+/// Describes when the revealed path determines the answer.
+fn solvability_summary(report: &SolvabilityReport, max_time: f64) -> String {
+    match report.earliest_time {
+        Some(time) => format!("Can be solved after {:.0}s of {:.0}s.", time, max_time),
+        None => "Unfair: the revealed path never determines the coefficients to 0.01.".to_string(),
+    }
+}
+
+/// The plot that shows the paths of a level, used both in game and in the level editor.
+fn level_plot(id_source: &str, level: &Level) -> Plot {
+    let mut plot = Plot::new(id_source).allow_drag(false).legend(Legend {
+        background_alpha: 0.5,
+        ..Default::default()
+    });
+    for limit in level.limits {
+        plot = plot.include_x(limit.x);
+        plot = plot.include_y(limit.y);
+    }
+    plot
+}
+
+/// The relative margin that egui adds around the bounds of a plot.
+const PLOT_MARGIN: f32 = 0.05;
+
+/// How close (in points) the pointer has to be to a handle to grab it.
+const HANDLE_GRAB_RADIUS: f32 = 12.0;
+
+/// Converts between positions on the screen and values of a plot that shows exactly the given bounds.
+///
+/// egui doesn't expose the transform of a plot, so this only works for plots that can't be zoomed
+/// and whose items are all inside the bounds.
+struct PlotTransform {
+    rect: egui::Rect,
+    min: [f64; 2],
+    max: [f64; 2],
+}
+
+impl PlotTransform {
+    fn new(rect: egui::Rect, [min, max]: [[f64; 2]; 2]) -> Self {
+        let margin = [0, 1].map(|axis| (max[axis] - min[axis]) * PLOT_MARGIN as f64);
+        Self {
+            rect,
+            min: [min[0] - margin[0], min[1] - margin[1]],
+            max: [max[0] + margin[0], max[1] + margin[1]],
+        }
+    }
+
+    fn value_from_position(&self, pos: egui::Pos2) -> [f64; 2] {
+        let x = (pos.x - self.rect.left()) as f64 / self.rect.width() as f64;
+        // The y axis of the screen points down.
+        let y = (self.rect.bottom() - pos.y) as f64 / self.rect.height() as f64;
+        [
+            self.min[0] + x * (self.max[0] - self.min[0]),
+            self.min[1] + y * (self.max[1] - self.min[1]),
+        ]
+    }
+
+    fn position_from_value(&self, [x, y]: [f64; 2]) -> egui::Pos2 {
+        let x = (x - self.min[0]) / (self.max[0] - self.min[0]);
+        let y = (y - self.min[1]) / (self.max[1] - self.min[1]);
+        egui::pos2(
+            self.rect.left() + x as f32 * self.rect.width(),
+            self.rect.bottom() - y as f32 * self.rect.height(),
+        )
+    }
+}
+
+/// The area shown by the plot while it can be clicked: the limits of the level,
+/// and the points outside of them that must stay visible.
+fn plot_bounds(level: &Level, points: &[[f64; 2]]) -> [[f64; 2]; 2] {
+    let [min, max] = level.limits;
+    let mut bounds = [[min.x as f64, min.y as f64], [max.x as f64, max.y as f64]];
+    for point in points {
+        for axis in 0..2 {
+            bounds[0][axis] = bounds[0][axis].min(point[axis]);
+            bounds[1][axis] = bounds[1][axis].max(point[axis]);
+        }
+    }
+    bounds
+}
+
+/// Points on the plot that the player can drag, with the prediction going through all of them.
+#[derive(Default)]
+struct PlotHandles {
+    enabled: bool,
+    points: Vec<[f64; 2]>,
+    /// The index of the handle that is being dragged.
+    dragged: Option<usize>,
+}
+
+impl PlotHandles {
+    /// Moves the handles onto the prediction, which may have been changed with the other controls.
+    fn follow_prediction(&mut self, level: &Level) {
+        let [min, max] = level.limits;
+        let x_range = min.x as f64..=max.x as f64;
+        if self.points.len() != level.player_path.params().len()
+            || self.points.iter().any(|point| !x_range.contains(&point[0]))
+        {
+            self.points = level.prediction_handles();
+            self.dragged = None;
+        }
+        for point in &mut self.points {
+            point[1] = level.player_point(point[0])[1];
+        }
+    }
+
+    /// Grabs the handle under the pointer, and moves the prediction with it while it is dragged.
+    /// Handles stay inside the plot, and don't move where no polynomial within the controls goes through them.
+    fn interact(&mut self, level: &mut Level, response: &egui::Response, bounds: [[f64; 2]; 2]) {
+        let transform = PlotTransform::new(response.rect, bounds);
+        let pointer = match response.interact_pointer_pos() {
+            Some(pointer) => pointer,
+            None => return,
+        };
+        if response.drag_started() {
+            self.dragged = self
+                .points
+                .iter()
+                .map(|point| transform.position_from_value(*point).distance(pointer))
+                .enumerate()
+                .filter(|(_, distance)| *distance <= HANDLE_GRAB_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i);
+        }
+        if let (true, Some(i)) = (response.dragged(), self.dragged) {
+            let value = transform.value_from_position(pointer);
+            let mut points = self.points.clone();
+            points[i] = [0, 1].map(|axis| value[axis].clamp(bounds[0][axis], bounds[1][axis]));
+            if level.fit_handles(&points) {
+                self.points = points;
+            }
+        }
+        if response.drag_released() {
+            self.dragged = None;
+        }
+    }
+}
+
+/// Points of the revealed enemy path marked by the player, for a [`Level::snap_fit`].
+#[derive(Default)]
+struct SnapFit {
+    /// Clicks on the plot mark points.
+    marking: bool,
+    points: Vec<[f64; 2]>,
+}
+
+impl SnapFit {
+    /// Marks the clicked point if it is on the revealed enemy path.
+    fn interact(&mut self, level: &Level, response: &egui::Response, bounds: [[f64; 2]; 2]) {
+        let transform = PlotTransform::new(response.rect, bounds);
+        let pointer = match (response.clicked(), response.interact_pointer_pos()) {
+            (true, Some(pointer)) => pointer,
+            _ => return,
+        };
+        let on_path = level.domain_range_time(0.01).any(|t| {
+            transform
+                .position_from_value(level.enemy_point(t))
+                .distance(pointer)
+                <= HANDLE_GRAB_RADIUS
+        });
+        if on_path {
+            self.points.push(transform.value_from_position(pointer));
+        }
+    }
+}
+
+/// The state of the controls of a component of the prediction.
+#[derive(Default)]
+struct ComponentControls {
+    /// The text of the [`equation_input`].
+    equation: String,
+    /// How the parameters of a polynomial are shown.
+    form: PolynomialForm,
+    /// The point `p` of the Taylor form.
+    center: f64,
+    /// The parameters of the form, or empty if the prediction can't be written in the form.
+    form_params: Vec<f64>,
+}
+
+/// The controls of a polynomial component of the prediction, in the form chosen by the player.
+fn polynomial_controls(
+    ui: &mut egui::Ui,
+    kind: CurveKind,
+    index: usize,
+    component: &mut dyn Trajectory,
+    controls: &mut ComponentControls,
+) {
+    let len = component.params().len();
+    let degree = len - 1;
+    if !controls.form.is_available(degree) {
+        controls.form = PolynomialForm::Standard;
+    }
+    ui.horizontal(|ui| {
+        ui.label("Form:");
+        egui::ComboBox::from_id_source(("Form", index))
+            .selected_text(controls.form.to_string())
+            .show_ui(ui, |ui| {
+                for form in PolynomialForm::ALL {
+                    if form.is_available(degree) {
+                        ui.selectable_value(&mut controls.form, form, form.to_string());
+                    }
+                }
+            });
+    });
+    let (form, template) = (
+        controls.form,
+        controls.form.template(degree, kind.variable()),
+    );
+    ui.label(format!(
+        "Path: {}",
+        kind.component_formula(index, &template)
+    ));
+    if form == PolynomialForm::Taylor {
+        ui.add(
+            egui::DragValue::new(&mut controls.center)
+                .speed(0.1)
+                .prefix("p: "),
+        );
+    }
+    // The parameters are kept while they still describe the prediction,
+    // so roots don't swap places when one is dragged past another.
+    let coefs = component.params();
+    let scale = coefs.iter().fold(1.0_f64, |max, c| max.max(c.abs()));
+    let up_to_date = controls.form_params.len() == len
+        && form
+            .to_polynomial(&controls.form_params, controls.center)
+            .coefs()
+            .iter()
+            .zip(coefs)
+            .all(|(a, b)| (a - b).abs() <= 1e-9 * scale);
+    if !up_to_date {
+        let poly = Polynomial::new(coefs.iter().copied());
+        controls.form_params = form
+            .from_polynomial(&poly, controls.center)
+            .unwrap_or_default();
+    }
+    if controls.form_params.is_empty() {
+        ui.label(match form {
+            PolynomialForm::Factored => {
+                "The prediction has complex roots or a lower degree, so it can't be factored."
+            }
+            _ => "The prediction can't be written in this form.",
+        });
+    }
+    let mut changed = false;
+    for (i, (param, name)) in controls
+        .form_params
+        .iter_mut()
+        .zip(form.param_names(degree))
+        .enumerate()
+    {
+        let mut drag = egui::DragValue::new(param)
+            .speed(0.1)
+            .prefix(format!("{}: ", name));
+        if form == PolynomialForm::Standard {
+            drag = drag.clamp_range(component.param_range(i));
+        }
+        changed |= ui.add(drag).changed();
+    }
+    if changed {
+        let poly = form.to_polynomial(&controls.form_params, controls.center);
+        for (i, coef) in poly.coefs().iter().enumerate() {
+            let range = component.param_range(i);
+            component.params_mut()[i] = coef.clamp(*range.start(), *range.end());
+        }
+    }
+    equation_input(ui, index, component, &mut controls.equation);
+}
+
+/// A text box to type the equation of a polynomial component of the prediction.
+/// The coefficients are set as soon as the equation is valid, otherwise the problem is highlighted.
+fn equation_input(
+    ui: &mut egui::Ui,
+    index: usize,
+    component: &mut dyn Trajectory,
+    equation: &mut String,
+) {
+    let len = component.params().len();
+    let parsed = parse_coefficients(equation, len).and_then(|coefficients| {
+        match (0..len).find(|&i| !component.param_range(i).contains(&coefficients[i])) {
+            Some(i) => Err(ParseError {
+                message: format!(
+                    "{} = {} is outside of the controls",
+                    component.param_names()[i],
+                    coefficients[i]
+                ),
+                span: 0..equation.len(),
+            }),
+            None => Ok(coefficients),
+        }
+    });
+    let error = match &parsed {
+        Err(err) if !equation.trim().is_empty() => Some(err.clone()),
+        _ => None,
+    };
+    let response = ui
+        .horizontal(|ui| {
+            ui.label("Equation:");
+            ui.add(
+                egui::TextEdit::singleline(equation)
+                    .id_source(("Equation", index))
+                    .hint_text("e.g. (x - 1)(x + 2)")
+                    .text_color_opt(error.as_ref().map(|_| Color32::RED)),
+            )
+        })
+        .inner;
+    if let Some(err) = error {
+        // Show the equation again with the part that caused the error highlighted.
+        // Errors about missing input point past the end, which is shown as a space.
+        let (before, after) = equation.split_at(err.span.start.min(equation.len()));
+        let (highlighted, after) = after.split_at(err.span.len().min(after.len()));
+        let highlighted = if highlighted.is_empty() {
+            " "
+        } else {
+            highlighted
+        };
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            ui.add(egui::Label::new(before).monospace());
+            ui.add(
+                egui::Label::new(highlighted)
+                    .monospace()
+                    .background_color(Color32::from_rgb(150, 30, 30)),
+            );
+            ui.add(egui::Label::new(after).monospace());
+        });
+        ui.colored_label(Color32::RED, err.message);
+    } else if let (true, Ok(coefficients)) = (response.changed(), parsed) {
+        component.params_mut().copy_from_slice(&coefficients);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ui_ingame(
+    egui_ctx: ResMut<EguiContext>,
+    mut commands: Commands,
+    mut level: ResMut<Level>,
+    mut app_state: ResMut<State<AppState>>,
+    game_mode: Res<GameMode>,
+    mut random_levels: ResMut<RandomLevels>,
+    mut survival_run: ResMut<SurvivalRun>,
+    daily_challenge: Res<DailyChallenge>,
+    profile: Res<Profile>,
+    mut recorder: ResMut<ReplayRecorder>,
+    ghost: Res<Ghost>,
+    mut clock: ResMut<GameClock>,
+    time: Res<Time>,
+    mut controls: Local<Vec<ComponentControls>>,
+    mut handles: Local<PlotHandles>,
+    mut snap_fit: Local<SnapFit>,
+) {
+    let ctx = egui_ctx.ctx();
+    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+        // The top panel is often a good place for a menu bar:
+        egui::menu::bar(ui, |ui| {
+            egui::menu::menu(ui, "Options", |ui| {
+                if ui.button("Main Menu").clicked() {
+                    let _ = app_state.set(AppState::MainMenu);
+                }
+                if ui.button("Quit").clicked() {
+                    std::process::exit(0);
+                }
+            });
+            // Replays have their own speed, see `ui_replay`.
+            if app_state.current() != &AppState::Replay && !game_mode.is_ranked() {
+                egui::menu::menu(ui, "Game Speed", |ui| {
+                    for speed in GameClock::SPEEDS {
+                        if ui
+                            .selectable_label(clock.speed() == speed, format!("{}×", speed))
+                            .clicked()
+                        {
+                            clock.set_speed(speed);
+                        }
+                    }
+                });
+            }
+        });
+    });
+
+    let available_rect = ctx.available_rect();
+    let available_width = available_rect.width();
+    let available_height = available_rect.height();
+
+    let playing = app_state.current() == &AppState::InGame && !level.won && !level.lost;
+    // Game is displayed here.
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.set_enabled(playing);
+        if *game_mode == GameMode::Survival && app_state.current() != &AppState::Replay {
+            ui.label(format!(
+                "Survival level {}, score: {}",
+                survival_run.levels_cleared + 1,
+                survival_run.score
+            ));
+        }
+        let time_left = format!("Time left: {:.2}s", level.max_time - level.time_taken);
+        if clock.speed() == 1.0 || app_state.current() == &AppState::Replay {
+            ui.label(time_left);
+        } else {
+            ui.label(format!("{} ({}× speed)", time_left, clock.speed()));
+        }
+        ui.add(egui::widgets::ProgressBar::new(
+            (level.time_taken / level.max_time).clamp(0.0, 1.0) as f32,
+        ));
+        // Draw the background even when paused
+
+        // Calculate the paths for the player and enemy
+        let enemy_path = Line::new(Values::from_values_iter(level.domain_range_time(0.01).map(
+            |t| {
+                let [x, y] = level.enemy_point(t);
+                Value::new(x, y)
+            },
+        )))
+        .name("Enemy Path")
+        .color(Color32::RED)
+        .width(2.5_f32);
+
+        handles.enabled &= level.is_polynomial_graph();
+        snap_fit.marking &= level.is_polynomial_graph();
+        // Marks on the part of the path that isn't revealed are left over from a restart.
+        let revealed = level
+            .domain_range_time(0.01)
+            .fold([f64::INFINITY, f64::NEG_INFINITY], |[min, max], t| {
+                [min.min(t), max.max(t)]
+            });
+        snap_fit
+            .points
+            .retain(|point| (revealed[0] - 0.1..=revealed[1] + 0.1).contains(&point[0]));
+        if handles.enabled {
+            handles.follow_prediction(&level);
+        }
+        let bounds = match (handles.enabled, snap_fit.marking) {
+            (true, _) => Some(plot_bounds(&level, &handles.points)),
+            (false, true) => Some(plot_bounds(&level, &[])),
+            (false, false) => None,
+        };
+        // While the plot can be clicked, it must not grow to show points outside of its bounds.
+        let in_view = |[x, y]: [f64; 2]| {
+            bounds.is_none_or(|[min, max]| {
+                (min[0]..=max[0]).contains(&x) && (min[1]..=max[1]).contains(&y)
+            })
+        };
+
+        let player_path = Points::new(Values::from_values_iter(
+            level
+                // Bigger spacing because it's just points.
+                .domain_range_prediction(0.025)
+                .map(|t| level.player_point(t))
+                .filter(|point| in_view(*point))
+                .map(|[x, y]| Value::new(x, y)),
+        ))
+        .name("Prediction")
+        .color(Color32::GREEN)
+        .radius(2.5_f32);
+
+        let mut plot = match bounds {
+            Some([min, max]) => level_plot("clickable_paths", &level)
+                .allow_zoom(false)
+                .include_x(min[0])
+                .include_x(max[0])
+                .include_y(min[1])
+                .include_y(max[1]),
+            None => level_plot("rocket_paths", &level),
+        };
+        plot = plot.line(enemy_path).points(player_path);
+        // The ghost is the prediction of the best run at the same time taken.
+        let ghost_level = ghost
+            .run
+            .as_ref()
+            .filter(|_| profile.settings.show_ghost && app_state.current() != &AppState::Replay)
+            .map(|(_, ghost_level)| ghost_level);
+        if let Some(ghost_level) = ghost_level {
+            plot = plot.points(
+                Points::new(Values::from_values_iter(
+                    level
+                        .domain_range_prediction(0.025)
+                        .map(|t| ghost_level.player_point(t))
+                        .filter(|point| in_view(*point))
+                        .map(|[x, y]| Value::new(x, y)),
+                ))
+                .name("Ghost")
+                .color(Color32::from_rgba_unmultiplied(255, 255, 255, 70))
+                .radius(1.5_f32),
+            );
+        }
+        if handles.enabled {
+            plot = plot.points(
+                Points::new(Values::from_values_iter(
+                    handles.points.iter().map(|[x, y]| Value::new(*x, *y)),
+                ))
+                .name("Handles")
+                .color(Color32::YELLOW)
+                .filled(true)
+                .radius(6.0_f32),
+            );
+        }
+        if !snap_fit.points.is_empty() {
+            plot = plot.points(
+                Points::new(Values::from_values_iter(
+                    snap_fit
+                        .points
+                        .iter()
+                        .filter(|point| in_view(**point))
+                        .map(|[x, y]| Value::new(*x, *y)),
+                ))
+                .name("Marked points")
+                .color(Color32::from_rgb(255, 140, 0))
+                .shape(MarkerShape::Cross)
+                .radius(5.0_f32),
+            );
+        }
+        // Roots are easier to predict when they can be compared to where the enemy path crosses.
+        let main = level.player_path.components().len() - 1;
+        if controls.get(main).map(|controls| controls.form) == Some(PolynomialForm::Factored) {
+            let roots = level.player_path.main_component().roots();
+            plot = plot.points(
+                Points::new(Values::from_values_iter(
+                    roots
+                        .into_iter()
+                        .map(|t| level.player_point(t))
+                        .filter(|point| in_view(*point))
+                        .map(|[x, y]| Value::new(x, y)),
+                ))
+                .name("Predicted roots")
+                .color(Color32::GREEN)
+                .shape(MarkerShape::Diamond)
+                .radius(5.0_f32),
+            );
+        }
+        if level.shows_roots() {
+            plot = plot.points(
+                Points::new(Values::from_values_iter(
+                    [level.start(), level.end()]
+                        .iter()
+                        .map(|t| level.enemy_point(*t))
+                        .filter(|point| in_view(*point))
+                        .map(|[x, y]| Value::new(x, y)),
+                ))
+                .name("Enemy roots")
+                .color(Color32::RED)
+                .shape(MarkerShape::Diamond)
+                .radius(5.0_f32),
+            );
+        }
+        let response = ui.add(plot);
+        if let Some(bounds) = bounds {
+            if handles.enabled {
+                handles.interact(&mut level, &response, bounds);
+            }
+            if snap_fit.marking {
+                snap_fit.interact(&level, &response, bounds);
+            }
+        }
+
+        ctx.request_repaint();
+    });
+
+    // Control window
+    let mut frame = Frame::window(&ctx.style());
+    frame.fill =
+        Color32::from_rgba_premultiplied(frame.fill.r(), frame.fill.g(), frame.fill.b(), 100);
+    egui::Window::new("Controls")
+        .frame(frame)
+        .default_pos(egui::pos2(40., 100.))
+        .show(ctx, |ui| {
+            ui.set_enabled(playing);
+            ui.label("Change the path to match that of your enemy using the controls.");
+            if level.is_polynomial_graph() {
+                ui.checkbox(&mut handles.enabled, "Drag handles on the plot")
+                    .on_hover_text("The prediction goes through every handle.");
+                ui.checkbox(&mut snap_fit.marking, "Mark points on the enemy path")
+                    .on_hover_text("Click on the revealed path to mark points for a snap fit.");
+                let needed = level.player_path.params().len();
+                ui.horizontal(|ui| {
+                    let snap =
+                        egui::Button::new(format!("Snap fit (+{:.0}s)", level.snap_fit_cost))
+                            .enabled(snap_fit.points.len() >= needed);
+                    if ui
+                        .add(snap)
+                        .on_hover_text(format!(
+                            "Fit the prediction to the marked points, needs at least {}.",
+                            needed
+                        ))
+                        .clicked()
+                    {
+                        recorder.record(&level, ReplayEvent::SnapFit(snap_fit.points.clone()));
+                        if level.snap_fit(&snap_fit.points) {
+                            snap_fit.marking = false;
+                            snap_fit.points.clear();
+                        }
+                    }
+                    if ui.button("Clear points").clicked() {
+                        snap_fit.points.clear();
+                    }
+                    ui.label(format!("{} marked", snap_fit.points.len()));
+                });
+            }
+            let kind = level.player_path.kind();
+            let templates = level.player_path.templates();
+            controls.resize_with(templates.len(), ComponentControls::default);
+            // Each component of the path is predicted separately.
+            for (index, ((component, template), controls)) in level
+                .player_path
+                .components_mut()
+                .iter_mut()
+                .zip(templates)
+                .zip(controls.iter_mut())
+                .enumerate()
+            {
+                if component.as_polynomial().is_some() {
+                    polynomial_controls(ui, kind, index, component.as_mut(), controls);
+                    continue;
+                }
+                ui.label(format!("Path: {}", template));
+                for (i, name) in component.param_names().iter().enumerate() {
+                    let range = component.param_range(i);
+                    ui.add(
+                        egui::DragValue::new(&mut component.params_mut()[i])
+                            .clamp_range(range)
+                            .speed(0.1)
+                            .prefix(format!("{}: ", name)),
+                    );
+                }
+            }
+            if kind != CurveKind::Graph {
+                ui.label(format!(
+                    "The path goes from {} = {:.2} to {:.2}.",
+                    kind.variable(),
+                    level.start(),
+                    level.end()
+                ));
+            }
+            for formula in level.player_path.formulas(1) {
+                ui.label(format!("Prediction: {}", formula));
+            }
+            if ui
+                .button("Confirm")
+                .on_hover_text("Confirm path prediction.")
+                .on_hover_text("Incorrect prediction will result in a time penalty.")
+                .clicked()
+            {
+                recorder.record(&level, ReplayEvent::Confirm);
+                level.confirm();
+            }
+        });
+
+    // Hints window
+    if !level.hints().is_empty() {
+        let mut frame = Frame::window(&ctx.style());
+        frame.fill =
+            Color32::from_rgba_premultiplied(frame.fill.r(), frame.fill.g(), frame.fill.b(), 100);
+        egui::Window::new("Hints")
+            .frame(frame)
+            .default_pos(egui::pos2(40., 500.))
+            .show(ctx, |ui| {
+                ui.set_enabled(playing);
+                for &i in level.hints_used() {
+                    ui.label(format!("• {}", level.hint_text(i)));
+                }
+                // Hints are offered in order, so the author can start with the cheap and vague ones.
+                let available = (0..level.hints().len())
+                    .filter(|&i| !level.is_hint_used(i))
+                    .collect::<Vec<_>>();
+                for &i in &available {
+                    let hint = &level.hints()[i];
+                    let button = egui::Button::new(format!("{} (+{:.0}s)", hint.kind, hint.cost))
+                        .enabled(Some(&i) == available.first());
+                    if ui
+                        .add(button)
+                        .on_hover_text("Use this hint. Its cost is added to the time taken.")
+                        .clicked()
+                    {
+                        recorder.record(&level, ReplayEvent::Hint(i));
+                        level.use_hint(i);
+                    }
+                }
+            });
+    }
+
+    // Pause Window
+    let mut frame = Frame::window(&ctx.style());
+    frame.margin = egui::vec2(50., 20.);
+    frame.fill =
         Color32::from_rgba_premultiplied(frame.fill.r(), frame.fill.g(), frame.fill.b(), 100);
     if app_state.current() == &AppState::Paused {
         egui::Window::new("Paused")
@@ -500,7 +2418,17 @@ fn ui_ingame(
                     {
                         let _ = app_state.set(AppState::InGame);
                     }
-                    if ui
+                    if *game_mode == GameMode::Survival {
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("New Run"))
+                            .on_hover_text("Start survival mode over. This run will be lost.")
+                            .clicked()
+                        {
+                            *level =
+                                start_survival_run(&mut survival_run, &mut random_levels, &time);
+                            let _ = app_state.set(AppState::InGame);
+                        }
+                    } else if ui
                         .add_sized(widget_size, egui::Button::new("Restart"))
                         .on_hover_text("Restart the level. Any progress will be lost.")
                         .clicked()
@@ -525,7 +2453,22 @@ fn ui_ingame(
             });
     }
 
-    if !level.won && !level.lost || app_state.current() == &AppState::Paused {
+    if !level.won && !level.lost
+        || app_state.current() == &AppState::Paused
+        || app_state.current() == &AppState::Replay
+    {
+        return;
+    }
+    if *game_mode == GameMode::Survival {
+        ui_survival_transition(
+            ctx,
+            &mut level,
+            &mut app_state,
+            &mut survival_run,
+            &mut random_levels,
+            &profile,
+            &time,
+        );
         return;
     }
     // Win-lose window
@@ -538,6 +2481,7 @@ fn ui_ingame(
         // Redish
         frame.fill = Color32::from_rgba_premultiplied(90, 30, 20, 150);
     }
+    let result = LevelResult::new(&level);
     egui::Window::new("Game Over")
         .frame(frame)
         .title_bar(false)
@@ -551,32 +2495,115 @@ fn ui_ingame(
                 } else {
                     ui.heading("You lose!");
                 }
+                let condition = level.win_condition;
+                ui.label(format!(
+                    "{} error: {:.3} (at most {} to win)",
+                    condition.criterion,
+                    level.prediction_error(),
+                    condition.tolerance
+                ));
+                if !level.hints_used().is_empty() {
+                    ui.label(format!(
+                        "Hints used: {} (+{:.0}s)",
+                        level.hints_used().len(),
+                        level.hint_cost()
+                    ));
+                }
+                if result.won {
+                    ui.heading(format!("{} {}", star_text(result.stars), result.score));
+                    egui::Grid::new("Score Breakdown").show(ui, |ui| {
+                        for (description, points) in result.breakdown() {
+                            ui.label(description);
+                            ui.label(format!("{:+.0}", points));
+                            ui.end_row();
+                        }
+                    });
+                    let stars = level.stars;
+                    ui.label(format!(
+                        "Stars at {}, {} and {} points.",
+                        stars.one, stars.two, stars.three
+                    ));
+                }
             });
             ui.vertical(|ui| {
                 ui.add_space(20.);
                 ui.spacing_mut().item_spacing = egui::vec2(30., 30.);
                 let widget_size = size_to_center_widgets(
                     egui::vec2(available_width, available_height),
-                    egui::vec2(1.0, 5.0),
+                    egui::vec2(1.0, 6.0),
                     ui.spacing().item_spacing,
                 );
-                if level.lost {
+                if let Some(replay) = recorder.replay.as_ref() {
                     if ui
-                        .add_sized(widget_size, egui::Button::new("Restart"))
-                        .on_hover_text("Restart the level. Any progress will be lost.")
+                        .add_sized(widget_size, egui::Button::new("Watch Replay"))
+                        .on_hover_text("Watch how the level was played.")
                         .clicked()
                     {
-                        level.restart();
-                        let _ = app_state.set(AppState::InGame);
+                        commands.insert_resource(ReplayPlayer::new(
+                            replay.clone(),
+                            app_state.current().clone(),
+                        ));
+                        let _ = app_state.set(AppState::Replay);
                     }
                 }
-
-                if ui
-                    .add_sized(widget_size, egui::Button::new("Level Menu"))
-                    .on_hover_text("Select a level to play.")
-                    .clicked()
+                if level.lost
+                    && ui
+                        .add_sized(widget_size, egui::Button::new("Restart"))
+                        .on_hover_text("Restart the level. Any progress will be lost.")
+                        .clicked()
                 {
-                    let _ = app_state.set(AppState::LevelMenu);
+                    level.restart();
+                    let _ = app_state.set(AppState::InGame);
+                }
+
+                match *game_mode {
+                    GameMode::Random => {
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("Next Level"))
+                            .on_hover_text("Play a new random level.")
+                            .clicked()
+                        {
+                            *level = random_levels.next_level();
+                        }
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("World Map"))
+                            .on_hover_text("Select a level to play.")
+                            .clicked()
+                        {
+                            let _ = app_state.set(AppState::WorldMap);
+                        }
+                    }
+                    GameMode::Levels => {
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("Level Menu"))
+                            .on_hover_text("Select a level to play.")
+                            .clicked()
+                        {
+                            let _ = app_state.set(AppState::LevelMenu);
+                        }
+                    }
+                    GameMode::PlayTest => {
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("Level Editor"))
+                            .on_hover_text("Continue editing the level.")
+                            .clicked()
+                        {
+                            let _ = app_state.set(AppState::LevelEditor);
+                        }
+                    }
+                    GameMode::Daily => {
+                        let share_text = share_text(daily_challenge.date, &result);
+                        ui.label(&share_text);
+                        if ui
+                            .add_sized(widget_size, egui::Button::new("Copy Result"))
+                            .on_hover_text("Copy the result to share it.")
+                            .clicked()
+                        {
+                            ui.output().copied_text = share_text;
+                        }
+                    }
+                    // Survival mode has its own window, see `ui_survival_transition`.
+                    GameMode::Survival => {}
                 }
                 if ui
                     .add_sized(widget_size, egui::Button::new("Main Menu"))
@@ -594,3 +2621,64 @@ fn ui_ingame(
             });
         });
 }
+
+/// Plays the [`ReplayPlayer`] back through `ui_ingame`, with controls for its speed and time.
+fn ui_replay(
+    egui_ctx: ResMut<EguiContext>,
+    mut player: ResMut<ReplayPlayer>,
+    mut level: ResMut<Level>,
+    mut app_state: ResMut<State<AppState>>,
+    time: Res<Time>,
+) {
+    let end_time = player.replay.end_time;
+    if player.playing {
+        player.time = (player.time + time.delta_seconds_f64() * player.speed).min(end_time);
+        player.playing = player.time < end_time;
+    }
+    let mut exit = false;
+    egui::Window::new("Replay")
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-20.0, 40.0))
+        .show(egui_ctx.ctx(), |ui| {
+            ui.heading(&player.replay.title);
+            ui.horizontal(|ui| {
+                let text = if player.playing { "Pause" } else { "Play" };
+                if ui.button(text).clicked() {
+                    // Playing from the end starts over.
+                    if !player.playing && player.time >= end_time {
+                        player.time = 0.0;
+                    }
+                    player.playing = !player.playing;
+                }
+                for speed in GameClock::SPEEDS {
+                    ui.selectable_value(&mut player.speed, speed, format!("{}×", speed));
+                }
+            });
+            ui.add(
+                egui::Slider::new(&mut player.time, 0.0..=end_time)
+                    .text("Time taken")
+                    .suffix("s")
+                    .max_decimals(1),
+            );
+            if level.won {
+                ui.label("The level was won.");
+            } else if level.lost {
+                ui.label("The level was lost.");
+            }
+            if ui.button("Exit").clicked() {
+                exit = true;
+            }
+        });
+    if exit {
+        // The level that was played is left as it ended, e.g. for its Game Over window.
+        player.time = end_time;
+        let _ = app_state.set(player.return_state.clone());
+    }
+    match player.replay.level_at(player.time) {
+        Ok(replay_level) => *level = replay_level,
+        Err(err) => {
+            eprintln!("Could not play the replay: {}", err);
+            let _ = app_state.set(player.return_state.clone());
+        }
+    }
+}
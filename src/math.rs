@@ -0,0 +1,246 @@
+use std::ops::{Index, IndexMut};
+
+/// A dense matrix, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0.0; rows * cols],
+        }
+    }
+
+    /// The Vandermonde matrix of the points `xs` for polynomials with `len` coefficients.
+    ///
+    /// Columns go from the highest power to the constant term, like the coefficients of a [`Polynomial`](crate::polynomial::Polynomial),
+    /// so multiplying the matrix with the coefficients evaluates the polynomial at the points.
+    pub fn vandermonde(xs: &[f64], len: usize) -> Self {
+        let mut matrix = Matrix::zeros(xs.len(), len);
+        for (i, x) in xs.iter().enumerate() {
+            let mut power = 1.0;
+            for j in (0..len).rev() {
+                matrix[(i, j)] = power;
+                power *= x;
+            }
+        }
+        matrix
+    }
+
+    /// Multiplies the matrix with the column vector `x`.
+    pub fn mul_vec(&self, x: &[f64]) -> Vec<f64> {
+        assert_eq!(x.len(), self.cols, "Needs a value for every column.");
+        (0..self.rows)
+            .map(|i| (0..self.cols).map(|j| self[(i, j)] * x[j]).sum())
+            .collect()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    fn index(&self, (row, col): (usize, usize)) -> &f64 {
+        &self.data[row * self.cols + col]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut f64 {
+        &mut self.data[row * self.cols + col]
+    }
+}
+
+/// The QR decomposition of a matrix with at least as many rows as columns,
+/// computed with Householder reflections.
+#[derive(Debug, Clone)]
+pub struct Qr {
+    /// The Householder vectors below the diagonal, and R above the diagonal.
+    qr: Matrix,
+    /// The diagonal of R.
+    r_diag: Vec<f64>,
+    /// The lengths of the columns of the matrix.
+    col_norms: Vec<f64>,
+}
+
+impl Qr {
+    /// # Panics
+    /// If the matrix has fewer rows than columns.
+    pub fn new(matrix: &Matrix) -> Self {
+        assert!(
+            matrix.rows >= matrix.cols,
+            "QR decomposition needs at least as many rows as columns."
+        );
+        let mut qr = matrix.clone();
+        let col_norms = (0..qr.cols)
+            .map(|j| (0..qr.rows).map(|i| qr[(i, j)].powi(2)).sum::<f64>().sqrt())
+            .collect();
+        let mut r_diag = vec![0.0; qr.cols];
+        for k in 0..qr.cols {
+            let norm = (k..qr.rows)
+                .map(|i| qr[(i, k)] * qr[(i, k)])
+                .sum::<f64>()
+                .sqrt();
+            if norm == 0.0 {
+                continue;
+            }
+            // Choose the sign that avoids cancellation.
+            let norm = if qr[(k, k)] < 0.0 { -norm } else { norm };
+            for i in k..qr.rows {
+                qr[(i, k)] /= norm;
+            }
+            qr[(k, k)] += 1.0;
+            // Apply the reflection to the remaining columns.
+            for j in k + 1..qr.cols {
+                let s = -(k..qr.rows).map(|i| qr[(i, k)] * qr[(i, j)]).sum::<f64>() / qr[(k, k)];
+                for i in k..qr.rows {
+                    let v = qr[(i, k)];
+                    qr[(i, j)] += s * v;
+                }
+            }
+            r_diag[k] = -norm;
+        }
+        Self {
+            qr,
+            r_diag,
+            col_norms,
+        }
+    }
+
+    /// The diagonal of the upper triangular factor R.
+    pub fn r_diagonal(&self) -> &[f64] {
+        &self.r_diag
+    }
+
+    /// Whether the columns of the matrix are linearly independent.
+    ///
+    /// The `k`th value of R is the part of column `k` that isn't in the span of the columns before it.
+    /// Rounding errors leave a tiny part instead of zero for a dependent column,
+    /// so a column counts as dependent when that part is below `sqrt(ε)` of its length.
+    /// For the Vandermonde matrices of the game, rounding stays below about `1e-9` of the length,
+    /// while points that are 0.01 apart keep more than `1e-6`.
+    pub fn is_full_rank(&self) -> bool {
+        let tolerance = f64::EPSILON.sqrt();
+        self.r_diag
+            .iter()
+            .zip(&self.col_norms)
+            .all(|(r, norm)| *norm > 0.0 && r.abs() > tolerance * norm)
+    }
+
+    /// The least squares solution `x` of `Ax = b`, i.e. the `x` that minimizes `|Ax - b|`.
+    /// Returns `None` if the solution isn't unique.
+    ///
+    /// # Panics
+    /// If `b` doesn't have a value for every row of A.
+    pub fn solve_least_squares(&self, b: &[f64]) -> Option<Vec<f64>> {
+        assert_eq!(b.len(), self.qr.rows, "Needs a value for every row.");
+        if !self.is_full_rank() {
+            return None;
+        }
+        let (rows, cols) = (self.qr.rows, self.qr.cols);
+        // Compute Qᵀb.
+        let mut y = b.to_vec();
+        for k in 0..cols {
+            let s = -(k..rows).map(|i| self.qr[(i, k)] * y[i]).sum::<f64>() / self.qr[(k, k)];
+            for (i, y) in y.iter_mut().enumerate().skip(k) {
+                *y += s * self.qr[(i, k)];
+            }
+        }
+        // Solve Rx = Qᵀb by back substitution.
+        let mut x = vec![0.0; cols];
+        for k in (0..cols).rev() {
+            let sum = (k + 1..cols).map(|j| self.qr[(k, j)] * x[j]).sum::<f64>();
+            x[k] = (y[k] - sum) / self.r_diag[k];
+        }
+        Some(x)
+    }
+
+    /// The lengths of the rows of `R⁻¹`.
+    ///
+    /// If the values of `b` have independent errors with standard deviation `σ`,
+    /// the `i`th value of the least squares solution has standard deviation `σ` times the `i`th length.
+    /// Returns `None` if the solution isn't unique.
+    pub fn inverse_r_row_norms(&self) -> Option<Vec<f64>> {
+        if !self.is_full_rank() {
+            return None;
+        }
+        let cols = self.qr.cols;
+        // Invert R one column at a time, R⁻¹ is upper triangular as well.
+        let mut inverse = Matrix::zeros(cols, cols);
+        for j in 0..cols {
+            inverse[(j, j)] = 1.0 / self.r_diag[j];
+            for i in (0..j).rev() {
+                let sum = (i + 1..=j)
+                    .map(|k| self.qr[(i, k)] * inverse[(k, j)])
+                    .sum::<f64>();
+                inverse[(i, j)] = -sum / self.r_diag[i];
+            }
+        }
+        Some(
+            (0..cols)
+                .map(|i| {
+                    (i..cols)
+                        .map(|j| inverse[(i, j)].powi(2))
+                        .sum::<f64>()
+                        .sqrt()
+                })
+                .collect(),
+        )
+    }
+
+    /// A cheap estimate of the condition number of the matrix,
+    /// the ratio between the largest and smallest diagonal values of R.
+    /// It is infinite if the columns are linearly dependent.
+    pub fn condition_estimate(&self) -> f64 {
+        let abs = self.r_diag.iter().map(|r| r.abs());
+        let max = abs.clone().fold(0.0, f64::max);
+        let min = abs.fold(f64::INFINITY, f64::min);
+        max / min
+    }
+
+    /// The base 10 logarithm of `sqrt(det(AᵀA))`, the volume scaling of the matrix A.
+    /// Returns negative infinity if the columns of A are linearly dependent.
+    pub fn log10_volume(&self) -> f64 {
+        self.r_diag.iter().map(|r| r.abs().log10()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_squares_line() {
+        // y = 2x + 1 exactly, and a line through three points that aren't on one.
+        let qr = Qr::new(&Matrix::vandermonde(&[0.0, 1.0, 2.0, 3.0], 2));
+        let fit = qr.solve_least_squares(&[1.0, 3.0, 5.0, 7.0]).unwrap();
+        assert!((fit[0] - 2.0).abs() < 1e-12 && (fit[1] - 1.0).abs() < 1e-12);
+        let qr = Qr::new(&Matrix::vandermonde(&[0.0, 1.0, 2.0], 2));
+        let fit = qr.solve_least_squares(&[0.0, 1.0, 1.0]).unwrap();
+        assert!((fit[0] - 0.5).abs() < 1e-12 && (fit[1] - 1.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rank_deficient() {
+        // Two different x values can't determine three coefficients.
+        let xs = [0.1, 0.1, 0.7, 0.7];
+        let qr = Qr::new(&Matrix::vandermonde(&xs, 3));
+        assert!(!qr.is_full_rank());
+        assert_eq!(qr.solve_least_squares(&[1.0, 1.0, 2.0, 2.0]), None);
+        assert_eq!(qr.inverse_r_row_norms(), None);
+        assert!(!Qr::new(&Matrix::zeros(3, 2)).is_full_rank());
+    }
+}
@@ -0,0 +1,510 @@
+use std::fmt;
+use std::ops::{Add, Index, IndexMut, Mul, Neg, Sub};
+
+/// Coefficients smaller than this (relative to the largest coefficient) are
+/// treated as zero when doing polynomial division.
+const ZERO_TOLERANCE: f64 = 1e-9;
+
+/// Two roots closer together than this are considered to be the same root
+/// when determining multiplicities.
+const MULTIPLE_ROOT_TOLERANCE: f64 = 1e-6;
+
+/// A polynomial with real coefficients.
+///
+/// Coefficients are stored from the highest degree to the constant term,
+/// so `[a, b, c]` is the polynomial `ax^2 + bx + c`.
+/// Leading zero coefficients are kept unless [`Polynomial::normalize`] is called,
+/// this way a polynomial can keep a fixed number of coefficients while it is being edited.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polynomial {
+    coefs: Vec<f64>,
+}
+
+/// A real root of a polynomial.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Root {
+    pub x: f64,
+    /// How many times the root is repeated, e.g. 2 for a double root.
+    pub multiplicity: usize,
+}
+
+impl Polynomial {
+    pub fn new(coefs: impl IntoIterator<Item = f64>) -> Self {
+        Self {
+            coefs: coefs.into_iter().collect(),
+        }
+    }
+
+    pub fn constant(value: f64) -> Self {
+        Self::new([value])
+    }
+
+    /// The polynomial `x`.
+    pub fn x() -> Self {
+        Self::new([1.0, 0.0])
+    }
+
+    /// The polynomial with `len` coefficients that are all equal to `value`.
+    pub fn filled(value: f64, len: usize) -> Self {
+        Self::new(vec![value; len])
+    }
+
+    pub fn coefs(&self) -> &[f64] {
+        &self.coefs
+    }
+
+    pub fn coefs_mut(&mut self) -> &mut [f64] {
+        &mut self.coefs
+    }
+
+    /// Number of stored coefficients, including leading zeros.
+    pub fn len(&self) -> usize {
+        self.coefs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coefs.is_empty()
+    }
+
+    /// The polynomial is zero everywhere.
+    pub fn is_zero(&self) -> bool {
+        self.coefs.iter().all(|c| *c == 0.0)
+    }
+
+    /// The degree of the polynomial, ignoring leading zeros.
+    /// The zero polynomial is considered to have degree 0.
+    pub fn degree(&self) -> usize {
+        self.normalized().len().saturating_sub(1)
+    }
+
+    /// Removes leading coefficients that are zero, as they don't change the polynomial.
+    pub fn normalize(&mut self) {
+        let first_non_zero = self
+            .coefs
+            .iter()
+            .position(|c| *c != 0.0)
+            .unwrap_or(self.coefs.len());
+        self.coefs.drain(..first_non_zero);
+    }
+
+    pub fn normalized(&self) -> Self {
+        let mut poly = self.clone();
+        poly.normalize();
+        poly
+    }
+
+    /// Pads the polynomial with leading zeros such that it has `len` coefficients.
+    /// Does nothing if there are already at least `len` coefficients.
+    pub fn pad_to(&mut self, len: usize) {
+        if self.coefs.len() < len {
+            let padding = len - self.coefs.len();
+            self.coefs.splice(0..0, std::iter::repeat_n(0.0, padding));
+        }
+    }
+
+    /// Evaluates the polynomial using Horner's rule.
+    pub fn eval(&self, x: f64) -> f64 {
+        let mut y = 0.;
+        for coef in &self.coefs {
+            y = coef + x * y;
+        }
+        y
+    }
+
+    pub fn derivative(&self) -> Self {
+        let degree = self.coefs.len().saturating_sub(1);
+        Self::new(
+            self.coefs
+                .iter()
+                .take(degree)
+                .enumerate()
+                .map(|(i, coef)| coef * (degree - i) as f64),
+        )
+    }
+
+    /// The antiderivative with a constant term of zero.
+    pub fn antiderivative(&self) -> Self {
+        let len = self.coefs.len();
+        Self::new(
+            self.coefs
+                .iter()
+                .enumerate()
+                .map(|(i, coef)| coef / (len - i) as f64)
+                .chain(std::iter::once(0.0)),
+        )
+    }
+
+    /// Returns the polynomial `self(inner(x))`.
+    pub fn compose(&self, inner: &Polynomial) -> Self {
+        // Horner's rule, but with polynomials instead of numbers.
+        let mut result = Polynomial::default();
+        for coef in &self.coefs {
+            result = &(&result * inner) + &Polynomial::constant(*coef);
+        }
+        result.normalized()
+    }
+
+    /// Divides `self` by `den`, returning the quotient and the remainder.
+    /// Coefficients of the remainder that are negligible compared to those of `self`
+    /// are set to zero.
+    ///
+    /// # Panics
+    /// If `den` is the zero polynomial.
+    pub fn div_rem(&self, den: &Polynomial) -> (Self, Self) {
+        let den = den.normalized();
+        assert!(!den.is_empty(), "Division by the zero polynomial.");
+        let scale = self.coefs.iter().fold(0.0_f64, |max, c| max.max(c.abs()));
+        let mut quotient = Vec::new();
+        let mut rem = self.normalized().coefs;
+        while rem.len() >= den.len() {
+            let factor = rem[0] / den.coefs[0];
+            for (r, d) in rem.iter_mut().zip(&den.coefs) {
+                *r -= factor * d;
+            }
+            rem.remove(0);
+            quotient.push(factor);
+        }
+        for r in rem.iter_mut() {
+            if r.abs() <= ZERO_TOLERANCE * scale {
+                *r = 0.0;
+            }
+        }
+        (Self::new(quotient), Self::new(rem).normalized())
+    }
+
+    /// Returns every real root of the polynomial, sorted from left to right.
+    ///
+    /// Roots are isolated using a Sturm sequence on the interval given by the
+    /// Cauchy bound, so roots are found regardless of where they lie and no matter
+    /// how close together they are. Each root is refined until it is known to lie
+    /// in an interval of width at most `tolerance`.
+    pub fn roots(&self, tolerance: f64) -> Vec<Root> {
+        let poly = self.normalized();
+        if poly.len() < 2 {
+            // Constant polynomials have no isolated roots.
+            return Vec::new();
+        }
+        let (simple, _) = poly.square_free();
+        let sturm = simple.sturm_sequence();
+        let bound = poly.cauchy_bound();
+        let mut roots = Vec::new();
+        isolate_roots(&sturm, -bound, bound, tolerance, &mut roots);
+        roots
+            .into_iter()
+            .map(|x| Root {
+                x,
+                multiplicity: poly.multiplicity(x),
+            })
+            .collect()
+    }
+
+    /// The smallest and largest value of the polynomial on `[start, end]`.
+    pub fn min_max(&self, start: f64, end: f64) -> [f64; 2] {
+        // The extremes are at the boundary or at a root of the derivative.
+        let candidates = self
+            .derivative()
+            .roots(MULTIPLE_ROOT_TOLERANCE)
+            .into_iter()
+            .map(|root| root.x)
+            .filter(|x| (start..=end).contains(x))
+            .chain([start, end]);
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for x in candidates {
+            let y = self.eval(x);
+            min = min.min(y);
+            max = max.max(y);
+        }
+        [min, max]
+    }
+
+    /// The Sturm sequence p, p', -rem(p, p'), ... of the polynomial.
+    /// The last element is the greatest common divisor of p and p'.
+    fn sturm_sequence(&self) -> Vec<Polynomial> {
+        let mut sequence = vec![self.clone(), self.derivative()];
+        loop {
+            let len = sequence.len();
+            let (_, rem) = sequence[len - 2].div_rem(&sequence[len - 1]);
+            if rem.is_empty() {
+                break;
+            }
+            sequence.push(-rem);
+        }
+        sequence
+    }
+
+    /// Splits the polynomial p into p / gcd(p, p') and gcd(p, p').
+    ///
+    /// The first polynomial has the same roots as p, but all of them are simple.
+    /// The second one has the multiple roots of p, with their multiplicity lowered by one.
+    fn square_free(&self) -> (Polynomial, Polynomial) {
+        let gcd = self.sturm_sequence().pop().unwrap();
+        (self.div_rem(&gcd).0, gcd)
+    }
+
+    /// All roots are strictly between `-bound` and `bound`.
+    fn cauchy_bound(&self) -> f64 {
+        1.0 + self.coefs[1..]
+            .iter()
+            .fold(0.0_f64, |max, c| max.max((c / self.coefs[0]).abs()))
+    }
+
+    /// The multiplicity of the root `x` of the polynomial.
+    fn multiplicity(&self, x: f64) -> usize {
+        let (_, gcd) = self.square_free();
+        if gcd.len() < 2 {
+            return 1;
+        }
+        let sturm = gcd.square_free().0.sturm_sequence();
+        if count_roots(
+            &sturm,
+            x - MULTIPLE_ROOT_TOLERANCE,
+            x + MULTIPLE_ROOT_TOLERANCE,
+        ) == 0
+        {
+            1
+        } else {
+            1 + gcd.multiplicity(x)
+        }
+    }
+
+    /// Creates a string like "ax^2 + bx + c" with a letter for each coefficient
+    /// of a polynomial of the given degree, written with `variable` instead of `x`.
+    pub fn template(degree: usize, variable: &str) -> String {
+        let mut equation = String::new();
+        for i in 0..=degree {
+            equation += &Polynomial::coef_name(i);
+            match degree - i {
+                0 => continue,
+                1 => equation += &format!("{} + ", variable),
+                n => equation += &format!("{}^{} + ", variable, n),
+            }
+        }
+        equation
+    }
+
+    /// The letter used for the coefficient at position `i`, i.e. 'a', 'b', 'c'...
+    pub fn coef_name(i: usize) -> String {
+        char::from_u32(97 + i as u32).unwrap().to_string()
+    }
+}
+
+/// Number of sign changes in the Sturm sequence evaluated at `x`, ignoring zeros.
+fn sign_changes(sturm: &[Polynomial], x: f64) -> usize {
+    let mut changes = 0;
+    let mut prev_sign = 0.0;
+    for poly in sturm {
+        let y = poly.eval(x);
+        if y == 0.0 {
+            continue;
+        }
+        if prev_sign * y < 0.0 {
+            changes += 1;
+        }
+        prev_sign = y.signum();
+    }
+    changes
+}
+
+/// Number of distinct real roots in the interval `(start, end]`.
+fn count_roots(sturm: &[Polynomial], start: f64, end: f64) -> usize {
+    sign_changes(sturm, start).saturating_sub(sign_changes(sturm, end))
+}
+
+/// Pushes the roots in `(start, end]` onto `roots`, in increasing order.
+/// Intervals are bisected until each root is known to lie in an interval of width at most `tolerance`.
+fn isolate_roots(sturm: &[Polynomial], start: f64, end: f64, tolerance: f64, roots: &mut Vec<f64>) {
+    let count = count_roots(sturm, start, end);
+    if count == 0 {
+        return;
+    }
+    let mut mid = (start + end) / 2.;
+    // Far from 0 the floats are too coarse to split an interval that is wider than `tolerance`.
+    if end - start <= tolerance || !(start < mid && mid < end) {
+        // If there is more than one root, they can't be separated at this precision anyway.
+        roots.push(mid);
+        return;
+    }
+    if sturm[0].eval(mid) == 0.0 {
+        if count == 1 {
+            roots.push(mid);
+            return;
+        }
+        // Avoid splitting exactly at a root.
+        mid += (end - start) / 8.;
+    }
+    isolate_roots(sturm, start, mid, tolerance, roots);
+    isolate_roots(sturm, mid, end, tolerance, roots);
+}
+
+impl Index<usize> for Polynomial {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        &self.coefs[index]
+    }
+}
+
+impl IndexMut<usize> for Polynomial {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        &mut self.coefs[index]
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        Polynomial::new(self.coefs.into_iter().map(|c| -c))
+    }
+}
+
+impl Add for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &Polynomial) -> Polynomial {
+        let len = self.len().max(other.len());
+        let mut result = self.clone();
+        result.pad_to(len);
+        let offset = len - other.len();
+        for (i, coef) in other.coefs.iter().enumerate() {
+            result.coefs[offset + i] += coef;
+        }
+        result
+    }
+}
+
+impl Sub for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: &Polynomial) -> Polynomial {
+        self + &-other.clone()
+    }
+}
+
+impl Mul for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: &Polynomial) -> Polynomial {
+        if self.is_empty() || other.is_empty() {
+            return Polynomial::default();
+        }
+        let mut coefs = vec![0.0; self.len() + other.len() - 1];
+        for (i, a) in self.coefs.iter().enumerate() {
+            for (j, b) in other.coefs.iter().enumerate() {
+                coefs[i + j] += a * b;
+            }
+        }
+        Polynomial::new(coefs)
+    }
+}
+
+impl Mul<f64> for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, factor: f64) -> Polynomial {
+        Polynomial::new(self.coefs.iter().map(|c| c * factor))
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: Polynomial) -> Polynomial {
+        &self + &other
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: Polynomial) -> Polynomial {
+        &self - &other
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: Polynomial) -> Polynomial {
+        &self * &other
+    }
+}
+
+impl Mul<f64> for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, factor: f64) -> Polynomial {
+        &self * factor
+    }
+}
+
+/// Writes the polynomial as e.g. "2x^3 - 0.5x + 1", skipping zero terms.
+/// A precision can be given to round the coefficients: `format!("{:.2}", poly)`.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let degree = self.len().saturating_sub(1);
+        let mut first = true;
+        for (i, coef) in self.coefs.iter().enumerate() {
+            if *coef == 0.0 {
+                continue;
+            }
+            let power = degree - i;
+            let sign = if *coef < 0.0 { "-" } else { "+" };
+            if first {
+                if *coef < 0.0 {
+                    write!(f, "-")?;
+                }
+            } else {
+                write!(f, " {} ", sign)?;
+            }
+            first = false;
+            let abs = coef.abs();
+            // Coefficients of 1 are only written for the constant term.
+            if abs != 1.0 || power == 0 {
+                match f.precision() {
+                    Some(precision) => write!(f, "{:.*}", precision, abs)?,
+                    None => write!(f, "{}", abs)?,
+                }
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "x")?,
+                n => write!(f, "x^{}", n)?,
+            }
+        }
+        if first {
+            write!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_of_a_quadratic() {
+        let roots = Polynomial::new([1.0, 0.0, -4.0]).roots(1e-9);
+        let xs = roots.iter().map(|root| root.x).collect::<Vec<_>>();
+        assert_eq!(xs.len(), 2);
+        assert!((xs[0] + 2.0).abs() < 1e-8 && (xs[1] - 2.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn large_roots_stop_bisecting() {
+        // The largest roots are about 1e8, where floats are further apart than the tolerance.
+        for coefs in [[1e-7, -10.0, -1.0], [1.0, -1e8, -1.0]] {
+            let roots = Polynomial::new(coefs).roots(1e-9);
+            assert_eq!(roots.len(), 2, "{:?}", coefs);
+            let largest = roots.last().unwrap().x;
+            assert!(
+                (largest - 1e8).abs() / 1e8 < 1e-6,
+                "{:?}: {}",
+                coefs,
+                largest
+            );
+        }
+    }
+}
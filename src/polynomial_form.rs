@@ -0,0 +1,231 @@
+use std::fmt;
+
+use crate::polynomial::Polynomial;
+
+/// Roots of the factored form are refined until they are known up to this distance.
+const ROOT_TOLERANCE: f64 = 1e-12;
+
+/// Ways of writing a polynomial with as many parameters as it has coefficients.
+///
+/// The parameters of every form convert to and from the coefficients, up to rounding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PolynomialForm {
+    /// The coefficients, `ax^2 + bx + c`.
+    #[default]
+    Standard,
+    /// The leading coefficient and the roots, `k(x - r1)(x - r2)`.
+    /// Only polynomials whose roots are all real can be written like this.
+    Factored,
+    /// The leading coefficient and the vertex, `a(x - h)^2 + k`, only for quadratics.
+    Vertex,
+    /// The coefficients around a point `p`, `a(x - p)^2 + b(x - p) + c`.
+    Taylor,
+}
+
+impl PolynomialForm {
+    pub const ALL: [PolynomialForm; 4] = [
+        PolynomialForm::Standard,
+        PolynomialForm::Factored,
+        PolynomialForm::Vertex,
+        PolynomialForm::Taylor,
+    ];
+
+    /// Whether polynomials of the given degree can be written in this form.
+    pub fn is_available(self, degree: usize) -> bool {
+        self != PolynomialForm::Vertex || degree == 2
+    }
+
+    /// The names of the parameters of a polynomial of the given degree.
+    pub fn param_names(self, degree: usize) -> Vec<String> {
+        match self {
+            PolynomialForm::Standard | PolynomialForm::Taylor => {
+                (0..=degree).map(Polynomial::coef_name).collect()
+            }
+            PolynomialForm::Factored => std::iter::once("k".to_string())
+                .chain((1..=degree).map(|i| format!("r{}", i)))
+                .collect(),
+            PolynomialForm::Vertex => vec!["a".into(), "h".into(), "k".into()],
+        }
+    }
+
+    /// The formula of a polynomial of the given degree, with the names of the parameters
+    /// and `variable` instead of `x`.
+    pub fn template(self, degree: usize, variable: &str) -> String {
+        match self {
+            PolynomialForm::Standard => Polynomial::template(degree, variable),
+            PolynomialForm::Factored => std::iter::once("k".to_string())
+                .chain((1..=degree).map(|i| format!("({} - r{})", variable, i)))
+                .collect(),
+            PolynomialForm::Vertex => format!("a({} - h)^2 + k", variable),
+            PolynomialForm::Taylor => Polynomial::template(degree, &format!("({} - p)", variable)),
+        }
+    }
+
+    /// The parameters of the form for the polynomial, keeping its number of coefficients,
+    /// or `None` if it can't be written in this form.
+    /// Only the Taylor form uses the `center` `p`.
+    pub fn from_polynomial(self, poly: &Polynomial, center: f64) -> Option<Vec<f64>> {
+        let coefs = poly.coefs();
+        match self {
+            PolynomialForm::Standard => Some(coefs.to_vec()),
+            PolynomialForm::Factored => {
+                if poly.is_zero() {
+                    return Some(vec![0.0; coefs.len()]);
+                }
+                let roots = poly
+                    .roots(ROOT_TOLERANCE)
+                    .into_iter()
+                    .flat_map(|root| std::iter::repeat_n(root.x, root.multiplicity));
+                let params = std::iter::once(coefs[0]).chain(roots).collect::<Vec<_>>();
+                // The leading coefficient is zero or some roots are complex.
+                (coefs[0] != 0.0 && params.len() == coefs.len()).then_some(params)
+            }
+            PolynomialForm::Vertex => match *coefs {
+                [a, b, c] if a != 0.0 => {
+                    // Adding zero turns `-0.0` into `0.0`.
+                    let h = -b / (2.0 * a) + 0.0;
+                    Some(vec![a, h, c - a * h * h])
+                }
+                _ => None,
+            },
+            PolynomialForm::Taylor => {
+                let mut shifted = poly.compose(&Polynomial::new([1.0, center]));
+                shifted.pad_to(coefs.len());
+                Some(shifted.coefs().to_vec())
+            }
+        }
+    }
+
+    /// The polynomial with the given parameters of the form, see [`PolynomialForm::from_polynomial`].
+    pub fn to_polynomial(self, params: &[f64], center: f64) -> Polynomial {
+        let mut poly = match self {
+            PolynomialForm::Standard => Polynomial::new(params.iter().copied()),
+            PolynomialForm::Factored => params[1..]
+                .iter()
+                .fold(Polynomial::constant(params[0]), |poly, root| {
+                    &poly * &Polynomial::new([1.0, -root])
+                }),
+            PolynomialForm::Vertex => {
+                let [a, h, k] = [params[0], params[1], params[2]];
+                Polynomial::new([a, -2.0 * a * h, a * h * h + k])
+            }
+            PolynomialForm::Taylor => {
+                Polynomial::new(params.iter().copied()).compose(&Polynomial::new([1.0, -center]))
+            }
+        };
+        poly.pad_to(params.len());
+        poly
+    }
+}
+
+impl fmt::Display for PolynomialForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PolynomialForm::Standard => "Standard",
+            PolynomialForm::Factored => "Factored",
+            PolynomialForm::Vertex => "Vertex",
+            PolynomialForm::Taylor => "Taylor",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// Converts the polynomial to the form and back, which should give the same coefficients.
+    fn round_trip(form: PolynomialForm, coefs: &[f64], center: f64) -> Vec<f64> {
+        let poly = Polynomial::new(coefs.iter().copied());
+        let params = form
+            .from_polynomial(&poly, center)
+            .unwrap_or_else(|| panic!("{} form of {:?}", form, coefs));
+        assert_eq!(params.len(), coefs.len());
+        assert_close(form.to_polynomial(&params, center).coefs(), coefs);
+        params
+    }
+
+    #[test]
+    fn standard() {
+        for coefs in [
+            &[2.0, -3.0, 1.0][..],
+            &[0.0, 1.0, -1.0],
+            &[5.0],
+            &[0.0, 0.0],
+        ] {
+            assert_eq!(round_trip(PolynomialForm::Standard, coefs, 0.0), coefs);
+        }
+    }
+
+    #[test]
+    fn factored() {
+        // (x - 1)^2 (x + 2)
+        let params = round_trip(PolynomialForm::Factored, &[1.0, 0.0, -3.0, 2.0], 0.0);
+        assert_close(&params, &[1.0, -2.0, 1.0, 1.0]);
+        // -2(x - 0.5)(x + 3)
+        let params = round_trip(PolynomialForm::Factored, &[-2.0, -5.0, 3.0], 0.0);
+        assert_close(&params, &[-2.0, -3.0, 0.5]);
+        round_trip(PolynomialForm::Factored, &[0.0, 0.0, 0.0], 0.0);
+
+        let factored = |coefs: [f64; 3]| {
+            PolynomialForm::Factored.from_polynomial(&Polynomial::new(coefs), 0.0)
+        };
+        // x^2 + 1 has complex roots.
+        assert_eq!(factored([1.0, 0.0, 1.0]), None);
+        // The leading coefficient of x - 1 written with three coefficients is zero.
+        assert_eq!(factored([0.0, 1.0, -1.0]), None);
+    }
+
+    #[test]
+    fn vertex() {
+        // 2(x + 1)^2 - 3
+        let params = round_trip(PolynomialForm::Vertex, &[2.0, 4.0, -1.0], 0.0);
+        assert_close(&params, &[2.0, -1.0, -3.0]);
+        let params = round_trip(PolynomialForm::Vertex, &[-1.0, 0.0, 4.0], 0.0);
+        assert!(params[1].is_sign_positive());
+
+        let vertex = |coefs: &[f64]| {
+            PolynomialForm::Vertex.from_polynomial(&Polynomial::new(coefs.iter().copied()), 0.0)
+        };
+        assert_eq!(vertex(&[0.0, 1.0, -1.0]), None);
+        assert_eq!(vertex(&[1.0, 0.0, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn taylor() {
+        // x^2 - 2x + 3 = (x - 1)^2 + 2
+        let params = round_trip(PolynomialForm::Taylor, &[1.0, -2.0, 3.0], 1.0);
+        assert_close(&params, &[1.0, 0.0, 2.0]);
+        // x^3 around -2: (x + 2)^3 - 6(x + 2)^2 + 12(x + 2) - 8
+        let params = round_trip(PolynomialForm::Taylor, &[1.0, 0.0, 0.0, 0.0], -2.0);
+        assert_close(&params, &[1.0, -6.0, 12.0, -8.0]);
+        // The number of coefficients is kept when the leading coefficient is zero.
+        let params = round_trip(PolynomialForm::Taylor, &[0.0, 1.0, -1.0], 0.5);
+        assert_close(&params, &[0.0, 1.0, -0.5]);
+        assert_eq!(round_trip(PolynomialForm::Taylor, &[3.0], 7.0), [3.0]);
+    }
+
+    #[test]
+    fn param_names_match_the_coefficients() {
+        for form in PolynomialForm::ALL {
+            for degree in 0..5 {
+                if form.is_available(degree) {
+                    assert_eq!(form.param_names(degree).len(), degree + 1, "{}", form);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::daily::Date;
+use crate::level::Level;
+use crate::score::LevelResult;
+use crate::storage;
+use crate::survival::SurvivalRecord;
+
+/// The version of the profile that this build writes.
+/// Bump it when a change to the profile needs a step in [`Profile::migrate`].
+pub const PROFILE_VERSION: u32 = 1;
+
+/// The name of the profile in [`storage`].
+const PROFILE_FILE: &str = "profile.ron";
+
+/// Everything about the player that is kept between runs of the game.
+///
+/// New fields must use `#[serde(default)]`, so that older profiles still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// The version of the game's profile format that wrote this profile.
+    #[serde(default)]
+    pub version: u32,
+    /// The best result of each level, by the asset path of the level file.
+    #[serde(default)]
+    pub best_results: BTreeMap<String, LevelResult>,
+    /// The asset paths of the levels that the player can play.
+    #[serde(default)]
+    pub unlocked_levels: BTreeSet<String>,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub statistics: Statistics,
+    /// The best runs of survival mode, from the highest score down.
+    #[serde(default)]
+    pub survival_leaderboard: Vec<SurvivalRecord>,
+    /// The best result of each daily challenge, by its date like `2021-08-24`.
+    #[serde(default)]
+    pub daily_results: BTreeMap<String, LevelResult>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            version: PROFILE_VERSION,
+            best_results: BTreeMap::new(),
+            unlocked_levels: BTreeSet::new(),
+            settings: Settings::default(),
+            statistics: Statistics::default(),
+            survival_leaderboard: Vec::new(),
+            daily_results: BTreeMap::new(),
+        }
+    }
+}
+
+/// Preferences of the player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// The colours of the widgets: active, inactive, hovered, open and non-interactive.
+    #[serde(default = "default_colors")]
+    pub colors: [[u8; 3]; 5],
+    /// Whether the prediction of the best run of a level is shown while it is played again.
+    #[serde(default = "default_show_ghost")]
+    pub show_ghost: bool,
+}
+
+fn default_colors() -> [[u8; 3]; 5] {
+    [
+        [46, 86, 126],
+        [66, 92, 121],
+        [74, 119, 157],
+        [85, 91, 106],
+        [59, 59, 74],
+    ]
+}
+
+fn default_show_ghost() -> bool {
+    true
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            colors: default_colors(),
+            show_ghost: default_show_ghost(),
+        }
+    }
+}
+
+/// Totals over all the levels that were played to the end.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    #[serde(default)]
+    pub levels_played: usize,
+    #[serde(default)]
+    pub levels_won: usize,
+    #[serde(default)]
+    pub wrong_confirms: usize,
+    #[serde(default)]
+    pub hints_used: usize,
+    /// Time in seconds taken in levels, including the costs of hints and wrong Confirms.
+    #[serde(default)]
+    pub time_taken: f64,
+}
+
+impl Profile {
+    /// Reads a profile that was written by [`Profile::to_ron`], of this or an older version.
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let profile = ron::de::from_str::<Profile>(text).map_err(|e| e.to_string())?;
+        profile.migrate()
+    }
+
+    /// Reads the saved profile, or `Ok(None)` if there isn't one yet.
+    pub fn load() -> Result<Option<Profile>, String> {
+        storage::read(PROFILE_FILE)?
+            .map(|text| Profile::from_ron(&text))
+            .transpose()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        storage::write(PROFILE_FILE, &self.to_ron()?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Brings a profile of an older version up to [`PROFILE_VERSION`].
+    ///
+    /// Fields that were added only need a default, changes to existing fields need a step here.
+    fn migrate(mut self) -> Result<Self, String> {
+        if self.version > PROFILE_VERSION {
+            return Err(format!(
+                "The profile has version {}, but this version of the game only reads up to version {}.",
+                self.version, PROFILE_VERSION
+            ));
+        }
+        // Version 0 is a profile without a version, which has the fields of version 1.
+        self.version = PROFILE_VERSION;
+        Ok(self)
+    }
+
+    /// Adds a level that was played to the end to the statistics,
+    /// and keeps its result if it is the best one of the level file at `path`.
+    /// Returns whether it was the best one.
+    pub fn record(&mut self, level: &Level, path: Option<&str>) -> bool {
+        let statistics = &mut self.statistics;
+        statistics.levels_played += 1;
+        statistics.levels_won += level.won as usize;
+        statistics.wrong_confirms += level.wrong_confirms;
+        statistics.hints_used += level.hints_used().len();
+        statistics.time_taken += level.time_taken.min(level.max_time);
+
+        let path = match path {
+            Some(path) if level.won => path,
+            _ => return false,
+        };
+        self.unlocked_levels.insert(path.to_string());
+        let result = LevelResult::new(level);
+        let is_best = self
+            .best_results
+            .get(path)
+            .is_none_or(|best| result.is_better_than(best));
+        if is_best {
+            self.best_results.insert(path.to_string(), result);
+        }
+        is_best
+    }
+
+    /// Keeps the result of the daily challenge of `date`, if it is the best one of that day.
+    /// Returns whether it was the best one.
+    pub fn record_daily(&mut self, level: &Level, date: Date) -> bool {
+        let result = LevelResult::new(level);
+        let date = date.to_string();
+        let is_best = self
+            .daily_results
+            .get(&date)
+            .is_none_or(|best| result.is_better_than(best));
+        if is_best {
+            self.daily_results.insert(date, result);
+        }
+        is_best
+    }
+}
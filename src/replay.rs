@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+use crate::level::Level;
+use crate::level_file::LevelDefinition;
+use crate::storage;
+
+/// The version of the replay format that this build writes.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Something the player did during a level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    /// The prediction changed to these parameters, see [`crate::curve::Curve::params`].
+    Prediction(Vec<f64>),
+    /// The prediction was confirmed, see [`Level::confirm`].
+    Confirm,
+    /// The hint at this position was used, see [`Level::use_hint`].
+    Hint(usize),
+    /// The prediction was fit through these marked points, see [`Level::snap_fit`].
+    SnapFit(Vec<[f64; 2]>),
+    /// The game was paused, which doesn't change the level.
+    Pause,
+    Resume,
+}
+
+impl ReplayEvent {
+    pub fn apply(&self, level: &mut Level) {
+        match self {
+            ReplayEvent::Prediction(params) => level.player_path.set_params(params),
+            ReplayEvent::Confirm => {
+                level.confirm();
+            }
+            ReplayEvent::Hint(i) => {
+                level.use_hint(*i);
+            }
+            ReplayEvent::SnapFit(points) => {
+                level.snap_fit(points);
+            }
+            ReplayEvent::Pause | ReplayEvent::Resume => {}
+        }
+    }
+}
+
+/// A [`ReplayEvent`] with the time taken in the level when it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedEvent {
+    pub time: f64,
+    pub event: ReplayEvent,
+}
+
+/// A recording of a level, from the start until it was won or lost.
+///
+/// Replays are stored as RON, like level files:
+///
+/// ```ron
+/// (
+///     version: 1,
+///     title: "Warm Up",
+///     level: (pack: "", title: "", difficulty: "", coefficients: [-1.0, 0.0, 1.0], max_time: 100.0),
+///     events: [
+///         (time: 2.5, event: Prediction([-1.0, 0.0, 1.0])),
+///         (time: 3.0, event: Confirm),
+///     ],
+///     end_time: 3.0,
+/// )
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// The title that is shown while the replay is watched.
+    pub title: String,
+    /// The level as it was played, so the replay still works when the level file changes.
+    pub level: LevelDefinition,
+    /// The events in the order they happened.
+    pub events: Vec<TimedEvent>,
+    /// The time taken when the level was over.
+    pub end_time: f64,
+}
+
+impl Replay {
+    /// Starts recording the level from its current state.
+    pub fn new(level: &Level) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            title: String::new(),
+            level: LevelDefinition::from_level(level),
+            events: vec![TimedEvent {
+                time: level.time_taken,
+                event: ReplayEvent::Prediction(level.player_path.params()),
+            }],
+            end_time: level.time_taken,
+        }
+    }
+
+    pub fn push(&mut self, time: f64, event: ReplayEvent) {
+        self.events.push(TimedEvent { time, event });
+        self.end_time = self.end_time.max(time);
+    }
+
+    /// The last prediction that was recorded.
+    pub fn last_prediction(&self) -> Option<&[f64]> {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|event| match &event.event {
+                ReplayEvent::Prediction(params) => Some(params.as_slice()),
+                _ => None,
+            })
+    }
+
+    /// The prediction at `time`, e.g. to show it next to the prediction of a later run.
+    pub fn prediction_at(&self, time: f64) -> Option<&[f64]> {
+        self.events
+            .iter()
+            .take_while(|event| event.time <= time)
+            .filter_map(|event| match &event.event {
+                ReplayEvent::Prediction(params) => Some(params.as_slice()),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// The level as it was at `time`, by playing the events from the start.
+    pub fn level_at(&self, time: f64) -> Result<Level, String> {
+        let mut level = self.level.to_level()?;
+        for event in self.events.iter().take_while(|event| event.time <= time) {
+            level.time_taken = event.time;
+            event.event.apply(&mut level);
+        }
+        // Costs of hints and wrong confirms can put the time past `time`.
+        level.time_taken = level.time_taken.max(time).min(self.end_time);
+        if !level.won && level.time_taken >= level.max_time {
+            level.lost = true;
+        }
+        Ok(level)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let replay = ron::de::from_str::<Replay>(text).map_err(|e| e.to_string())?;
+        if replay.version > REPLAY_VERSION {
+            return Err(format!(
+                "The replay has version {}, but this version of the game only reads up to version {}.",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Reads the replay called `name` from [`storage`], or `Ok(None)` if there is no such replay.
+    pub fn load(name: &str) -> Result<Option<Self>, String> {
+        storage::read(&replay_file(name))?
+            .map(|text| Replay::from_ron(&text))
+            .transpose()
+    }
+
+    /// Keeps the replay in [`storage`] as `name`, replacing the replay that had that name.
+    pub fn save(&self, name: &str) -> Result<(), String> {
+        storage::write(&replay_file(name), &self.to_ron()?)
+    }
+}
+
+fn replay_file(name: &str) -> String {
+    format!("replays/{}.replay.ron", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::Curve;
+    use crate::hint::{Hint, HintKind};
+    use crate::polynomial::Polynomial;
+
+    /// Plays `frames` frames of a tenth of a second.
+    fn play(level: &mut Level, frames: usize) {
+        for _ in 0..frames {
+            level.advance_time(0.1);
+        }
+    }
+
+    /// Records the event and applies it to the level, like the game does.
+    fn record(replay: &mut Replay, level: &mut Level, event: ReplayEvent) {
+        replay.push(level.time_taken, event.clone());
+        event.apply(level);
+    }
+
+    fn new_level() -> Level {
+        let path = Curve::graph(Box::new(Polynomial::new([-1.0, 0.0, 1.0])));
+        let mut level = Level::new(path, 30.0).unwrap();
+        level
+            .set_hints(vec![
+                Hint::new(HintKind::ShowRoots),
+                Hint::new(HintKind::RevealCoefficient(2)),
+            ])
+            .unwrap();
+        level
+    }
+
+    fn assert_same(replayed: &Level, level: &Level) {
+        assert_eq!(replayed.won, level.won);
+        assert_eq!(replayed.lost, level.lost);
+        assert_eq!(replayed.time_taken, level.time_taken);
+        assert_eq!(replayed.wrong_confirms, level.wrong_confirms);
+        assert_eq!(replayed.hints_used(), level.hints_used());
+        assert_eq!(replayed.player_path.params(), level.player_path.params());
+    }
+
+    #[test]
+    fn replay_reproduces_a_won_level() {
+        let mut level = new_level();
+        let mut replay = Replay::new(&level);
+        play(&mut level, 15);
+        record(
+            &mut replay,
+            &mut level,
+            ReplayEvent::Prediction(vec![-1.0, 0.5, 1.0]),
+        );
+        play(&mut level, 8);
+        record(&mut replay, &mut level, ReplayEvent::Hint(1));
+        record(&mut replay, &mut level, ReplayEvent::Confirm);
+        play(&mut level, 5);
+        record(&mut replay, &mut level, ReplayEvent::Hint(1));
+        record(
+            &mut replay,
+            &mut level,
+            ReplayEvent::Prediction(vec![-1.0, 0.0, 1.0]),
+        );
+        record(&mut replay, &mut level, ReplayEvent::Confirm);
+        assert!(level.won);
+        assert_eq!(level.wrong_confirms, 1);
+        replay.end_time = level.time_taken;
+
+        let replayed = replay.level_at(replay.end_time).unwrap();
+        assert_same(&replayed, &level);
+        // Halfway the level wasn't over yet.
+        let halfway = replay.level_at(2.0).unwrap();
+        assert!(!halfway.won && !halfway.lost);
+        assert_eq!(halfway.time_taken, 2.0);
+        assert_eq!(halfway.player_path.params(), [-1.0, 0.5, 1.0]);
+        assert!(halfway.hints_used().is_empty());
+    }
+
+    #[test]
+    fn replay_reproduces_a_lost_level() {
+        let mut level = new_level();
+        let mut replay = Replay::new(&level);
+        record(&mut replay, &mut level, ReplayEvent::Hint(0));
+        play(&mut level, 10);
+        record(
+            &mut replay,
+            &mut level,
+            ReplayEvent::Prediction(vec![2.0, 0.0, -1.0]),
+        );
+        record(&mut replay, &mut level, ReplayEvent::Confirm);
+        play(&mut level, 500);
+        assert!(level.lost);
+        replay.end_time = level.time_taken;
+
+        let replayed = Replay::from_ron(&replay.to_ron().unwrap())
+            .unwrap()
+            .level_at(replay.end_time)
+            .unwrap();
+        assert_same(&replayed, &level);
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::level::Level;
+
+/// Points for winning a level at all.
+const WIN_POINTS: f64 = 500.0;
+
+/// Points for winning without using any time, scaled down with the time that is used.
+const TIME_POINTS: f64 = 1000.0;
+
+/// Points for a prediction without any error, scaled down to zero at the tolerance of the win condition.
+const ACCURACY_POINTS: f64 = 500.0;
+
+/// Points taken away for every Confirm of a wrong prediction.
+const WRONG_CONFIRM_POINTS: f64 = 50.0;
+
+/// Points taken away for every hint, on top of the time it costs.
+const HINT_POINTS: f64 = 100.0;
+
+/// The scores needed for one, two and three stars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StarThresholds {
+    pub one: u32,
+    pub two: u32,
+    pub three: u32,
+}
+
+impl StarThresholds {
+    /// The number of stars for a score, from 0 to 3.
+    pub fn stars(&self, score: u32) -> u8 {
+        [self.one, self.two, self.three]
+            .iter()
+            .filter(|threshold| score >= **threshold)
+            .count() as u8
+    }
+}
+
+impl Default for StarThresholds {
+    /// A win without mistakes gets one star, a quick and accurate one three.
+    fn default() -> Self {
+        Self {
+            one: 500,
+            two: 1000,
+            three: 1500,
+        }
+    }
+}
+
+/// Shows a number of stars out of three, like "★★☆".
+pub fn star_text(stars: u8) -> String {
+    (0..3).map(|i| if i < stars { '★' } else { '☆' }).collect()
+}
+
+/// How well a level went, once it is over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelResult {
+    pub won: bool,
+    /// Time in seconds that was left, after the costs of hints and wrong Confirms.
+    pub time_left: f64,
+    pub max_time: f64,
+    pub wrong_confirms: usize,
+    pub hints_used: usize,
+    /// The error of the prediction, measured by the win condition.
+    pub error: f64,
+    /// The largest error that wins the level.
+    pub tolerance: f64,
+    pub score: u32,
+    pub stars: u8,
+}
+
+impl LevelResult {
+    pub fn new(level: &Level) -> Self {
+        let mut result = Self {
+            won: level.won,
+            time_left: (level.max_time - level.time_taken).max(0.0),
+            max_time: level.max_time,
+            wrong_confirms: level.wrong_confirms,
+            hints_used: level.hints_used().len(),
+            error: level.prediction_error(),
+            tolerance: level.win_condition.tolerance,
+            score: 0,
+            stars: 0,
+        };
+        let points = result
+            .breakdown()
+            .iter()
+            .map(|(_, points)| points)
+            .sum::<f64>();
+        result.score = points.max(0.0).round() as u32;
+        result.stars = if result.won {
+            level.stars.stars(result.score)
+        } else {
+            0
+        };
+        result
+    }
+
+    /// The parts of the score, with a description of each.
+    /// A level that was lost doesn't score any points.
+    pub fn breakdown(&self) -> Vec<(String, f64)> {
+        if !self.won {
+            return Vec::new();
+        }
+        let accuracy = (1.0 - self.error / self.tolerance).clamp(0.0, 1.0);
+        vec![
+            ("Win".to_string(), WIN_POINTS),
+            (
+                format!("Time left: {:.1}s", self.time_left),
+                TIME_POINTS * self.time_left / self.max_time,
+            ),
+            (
+                format!("Accuracy: {:.0}%", accuracy * 100.0),
+                ACCURACY_POINTS * accuracy,
+            ),
+            (
+                format!("Wrong confirms: {}", self.wrong_confirms),
+                -WRONG_CONFIRM_POINTS * self.wrong_confirms as f64,
+            ),
+            (
+                format!("Hints used: {}", self.hints_used),
+                -HINT_POINTS * self.hints_used as f64,
+            ),
+        ]
+    }
+
+    /// Whether this result should replace the other as the best result of the level.
+    pub fn is_better_than(&self, other: &LevelResult) -> bool {
+        (self.won, self.score) > (other.won, other.score)
+    }
+}
@@ -0,0 +1,341 @@
+use crate::curve::CurveKind;
+use crate::level::DEFAULT_SNAP_FIT_COST;
+use crate::level_file::{ComponentDefinition, LevelDefinition};
+use crate::score::StarThresholds;
+use crate::trajectory::TrajectoryFamily;
+use crate::win_condition::{WinCondition, WinCriterion};
+
+/// The version of the share code format, stored in the first byte of every code.
+///
+/// Kinds of curves, families and criteria are stored by their position in their `ALL` list,
+/// so new ones must be added at the end of those lists, or the version must change.
+const SHARE_CODE_VERSION: u8 = 1;
+
+/// The characters of base64url, see RFC 4648.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// The name of the URL query parameter with a share code, like `index.html?level=<code>`.
+pub const SHARE_CODE_PARAM: &str = "level";
+
+/// Writes the definition as a short code that can be pasted in a chat or a link.
+///
+/// Only the puzzle itself is kept: the title, the enemy path, the time limit and the win condition.
+/// The bytes end in a checksum, so typos are noticed when the code is read.
+pub fn encode(definition: &LevelDefinition) -> String {
+    let mut bytes = vec![SHARE_CODE_VERSION];
+    // The title is cut off at 255 bytes, on a character boundary.
+    let mut title_len = definition.title.len().min(u8::MAX as usize);
+    while !definition.title.is_char_boundary(title_len) {
+        title_len -= 1;
+    }
+    bytes.push(title_len as u8);
+    bytes.extend_from_slice(&definition.title.as_bytes()[..title_len]);
+    bytes.push(index_of(&CurveKind::ALL, definition.curve));
+    write_component(&mut bytes, definition.family, &definition.coefficients);
+    match &definition.x_component {
+        Some(x) => {
+            bytes.push(1);
+            write_component(&mut bytes, x.family, &x.coefficients);
+        }
+        None => bytes.push(0),
+    }
+    bytes.extend_from_slice(&definition.max_time.to_le_bytes());
+    let condition = definition.win_condition;
+    bytes.push(index_of(&WinCriterion::ALL, condition.criterion));
+    bytes.extend_from_slice(&condition.tolerance.to_le_bytes());
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    to_base64url(&bytes)
+}
+
+/// Reads a code made by [`encode`]. Spaces and line breaks around and inside it are ignored.
+pub fn decode(code: &str) -> Result<LevelDefinition, String> {
+    let code = code.split_whitespace().collect::<String>();
+    let bytes = from_base64url(&code)?;
+    if bytes.len() < 5 {
+        return Err("The code is too short.".to_string());
+    }
+    let (data, checksum) = bytes.split_at(bytes.len() - 4);
+    if fnv1a(data).to_le_bytes() != checksum {
+        return Err("The code is damaged, check that it was copied completely.".to_string());
+    }
+    let mut reader = Reader { bytes: data };
+    let version = reader.u8()?;
+    if version != SHARE_CODE_VERSION {
+        return Err(format!(
+            "The code has version {}, but this version of the game only reads version {}.",
+            version, SHARE_CODE_VERSION
+        ));
+    }
+    let title_len = reader.u8()? as usize;
+    let title = String::from_utf8(reader.take(title_len)?.to_vec())
+        .map_err(|_| "The title in the code is invalid.".to_string())?;
+    let curve = reader.item(&CurveKind::ALL)?;
+    let (family, coefficients) = reader.component()?;
+    let x_component = match reader.u8()? {
+        0 => None,
+        _ => {
+            let (family, coefficients) = reader.component()?;
+            Some(ComponentDefinition {
+                family,
+                coefficients,
+            })
+        }
+    };
+    let max_time = reader.f64()?;
+    let win_condition = WinCondition {
+        criterion: reader.item(&WinCriterion::ALL)?,
+        tolerance: reader.f64()?,
+    };
+    if !reader.bytes.is_empty() {
+        return Err("The code is too long.".to_string());
+    }
+    Ok(LevelDefinition {
+        pack: "Shared".to_string(),
+        title,
+        difficulty: "Custom".to_string(),
+        curve,
+        family,
+        coefficients,
+        x_component,
+        max_time,
+        hints: Vec::new(),
+        win_condition,
+        snap_fit_cost: DEFAULT_SNAP_FIT_COST,
+        stars: StarThresholds::default(),
+    })
+}
+
+/// The share code in the query of a link, like `https://example.com/index.html?level=<code>`.
+pub fn code_from_link(link: &str) -> Option<&str> {
+    let query = link.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+    query.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        (name == SHARE_CODE_PARAM).then_some(value)
+    })
+}
+
+/// The share code in the URL of the page that runs the game.
+#[cfg(target_arch = "wasm32")]
+pub fn code_from_page_url() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    code_from_link(&search).map(str::to_string)
+}
+
+fn index_of<T: PartialEq>(all: &[T], item: T) -> u8 {
+    all.iter().position(|other| *other == item).unwrap() as u8
+}
+
+fn write_component(bytes: &mut Vec<u8>, family: TrajectoryFamily, params: &[f64]) {
+    bytes.push(index_of(&TrajectoryFamily::ALL, family));
+    bytes.push(params.len().min(u8::MAX as usize) as u8);
+    for param in params.iter().take(u8::MAX as usize) {
+        bytes.extend_from_slice(&param.to_le_bytes());
+    }
+}
+
+/// Reads the fields of a code from the front.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < len {
+            return Err("The code is too short.".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        let value = f64::from_le_bytes(bytes);
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err("The code contains a number that isn't finite.".to_string())
+        }
+    }
+
+    fn item<T: Copy>(&mut self, all: &[T]) -> Result<T, String> {
+        let index = self.u8()? as usize;
+        all.get(index)
+            .copied()
+            .ok_or_else(|| "The code was made by a newer version of the game.".to_string())
+    }
+
+    fn component(&mut self) -> Result<(TrajectoryFamily, Vec<f64>), String> {
+        let family = self.item(&TrajectoryFamily::ALL)?;
+        let len = self.u8()? as usize;
+        let params = (0..len).map(|_| self.f64()).collect::<Result<_, _>>()?;
+        Ok((family, params))
+    }
+}
+
+/// The 32-bit FNV-1a hash, which is enough to catch typos.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Base64url without padding, so the code can be used in a URL as it is.
+fn to_base64url(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = (group[0] as u32) << 16 | (group[1] as u32) << 8 | group[2] as u32;
+        // Three bytes make four characters, a partial chunk one character more than its bytes.
+        for i in 0..=chunk.len() {
+            let index = (bits >> (18 - 6 * i)) & 0x3f;
+            text.push(BASE64_ALPHABET[index as usize] as char);
+        }
+    }
+    text
+}
+
+fn from_base64url(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for c in text.chars() {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|a| *a as char == c)
+            .ok_or_else(|| format!("'{}' can't be part of a code.", c))?;
+        bits = bits << 6 | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(title: &str) -> LevelDefinition {
+        LevelDefinition {
+            pack: String::new(),
+            title: title.to_string(),
+            difficulty: String::new(),
+            curve: CurveKind::Parametric,
+            family: TrajectoryFamily::Polynomial,
+            coefficients: vec![-1.0, 0.0, 4.0],
+            x_component: Some(ComponentDefinition {
+                family: TrajectoryFamily::Polynomial,
+                coefficients: vec![1.0, 0.0, -3.0, 0.0],
+            }),
+            max_time: 120.0,
+            hints: Vec::new(),
+            win_condition: WinCondition {
+                criterion: WinCriterion::L2Distance,
+                tolerance: 0.02,
+            },
+            snap_fit_cost: DEFAULT_SNAP_FIT_COST,
+            stars: StarThresholds::default(),
+        }
+    }
+
+    /// A code of the bytes with a valid checksum.
+    fn code_of(mut bytes: Vec<u8>) -> String {
+        let checksum = fnv1a(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        to_base64url(&bytes)
+    }
+
+    #[test]
+    fn round_trip() {
+        // Titles of 0, 1 and 2 bytes end the bytes in a full chunk, and a chunk of 1 and 2 bytes.
+        let mut tail_lengths = Vec::new();
+        for title in ["", "a", "ab"] {
+            let code = encode(&definition(title));
+            tail_lengths.push(from_base64url(&code).unwrap().len() % 3);
+            let decoded = decode(&code).unwrap();
+            let original = definition(title);
+            assert_eq!(decoded.title, original.title);
+            assert_eq!(decoded.curve, original.curve);
+            assert_eq!(decoded.coefficients, original.coefficients);
+            let x = decoded.x_component.unwrap();
+            assert_eq!(x.coefficients, original.x_component.unwrap().coefficients);
+            assert_eq!(decoded.max_time, original.max_time);
+            assert_eq!(decoded.win_condition, original.win_condition);
+        }
+        tail_lengths.sort();
+        assert_eq!(tail_lengths, [0, 1, 2]);
+    }
+
+    #[test]
+    fn long_title_is_cut_on_a_character_boundary() {
+        // 'é' takes 2 bytes, so 255 bytes would end in the middle of one.
+        let title = "é".repeat(200);
+        let decoded = decode(&encode(&definition(&title))).unwrap();
+        assert_eq!(decoded.title, "é".repeat(127));
+    }
+
+    #[test]
+    fn whitespace_is_ignored() {
+        let code = encode(&definition("Loop"));
+        let (a, b) = code.split_at(10);
+        assert!(decode(&format!(" {}\n {} ", a, b)).is_ok());
+    }
+
+    #[test]
+    fn damaged_codes_are_rejected() {
+        let code = encode(&definition("Loop"));
+        let mut bytes = from_base64url(&code).unwrap();
+        bytes[3] ^= 1;
+        assert!(decode(&to_base64url(&bytes))
+            .unwrap_err()
+            .contains("damaged"));
+        assert!(decode(&code[..code.len() - 1]).is_err());
+        assert!(decode("abc$").unwrap_err().contains("'$'"));
+        assert!(decode("").is_err());
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut bytes = from_base64url(&encode(&definition("Loop"))).unwrap();
+        bytes.truncate(bytes.len() - 4);
+        bytes[0] = SHARE_CODE_VERSION + 1;
+        assert!(decode(&code_of(bytes)).unwrap_err().contains("version"));
+    }
+
+    #[test]
+    fn invalid_levels_are_rejected() {
+        let mut level = definition("Loop");
+        level.max_time = 0.0;
+        assert!(decode(&encode(&level)).unwrap().to_level().is_err());
+        let mut level = definition("Loop");
+        level.win_condition.tolerance = -1.0;
+        assert!(decode(&encode(&level)).unwrap().to_level().is_err());
+        assert!(decode(&encode(&definition("Loop")))
+            .unwrap()
+            .to_level()
+            .is_ok());
+    }
+
+    #[test]
+    fn code_in_a_link() {
+        let link = "https://example.com/index.html?mode=play&level=AbC-_9#top";
+        assert_eq!(code_from_link(link), Some("AbC-_9"));
+        assert_eq!(code_from_link("index.html?level=xyz"), Some("xyz"));
+        assert_eq!(code_from_link("index.html?levels=xyz"), None);
+        assert_eq!(code_from_link("index.html#level=xyz"), None);
+        assert_eq!(code_from_link("AbC-_9"), None);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::level::{Level, WIN_TOLERANCE};
+use crate::math::Qr;
+
+/// Number of evenly spaced moments at which the revealed path is checked.
+const STEPS: usize = 20;
+
+/// Number of halvings used to find the earliest time more precisely than the steps.
+const REFINEMENTS: usize = 8;
+
+/// How far off the player can read a point of the revealed path, about a pixel of the plot.
+const READING_ERROR: f64 = 0.01;
+
+/// Distance between the revealed points, the same as the path that is drawn in game.
+const SPACING: f64 = 0.01;
+
+/// How well the revealed part of the path determines the parameters at one moment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RevealStep {
+    /// Time in seconds since the start of the level.
+    pub time: f64,
+    /// Number of points of the path that are revealed.
+    pub points: usize,
+    /// Estimate of the condition number of the least squares problem.
+    pub condition: f64,
+    /// The largest difference between a parameter of the least squares fit and the enemy path.
+    pub fit_error: f64,
+    /// The largest standard deviation of a parameter of the fit,
+    /// if every revealed point is misread by about [`READING_ERROR`].
+    pub uncertainty: f64,
+}
+
+impl RevealStep {
+    /// Whether the parameters can only be a single answer accepted by the default win condition.
+    pub fn is_determined(&self) -> bool {
+        self.fit_error <= WIN_TOLERANCE && self.uncertainty <= WIN_TOLERANCE
+    }
+}
+
+/// Checks whether a level can be solved from the path that is revealed before time runs out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolvabilityReport {
+    /// The state of the revealed path at evenly spaced moments, ending at the time limit.
+    pub steps: Vec<RevealStep>,
+    /// The earliest time in seconds at which the parameters are determined,
+    /// or `None` if the level is unfair.
+    pub earliest_time: Option<f64>,
+}
+
+impl SolvabilityReport {
+    /// Simulates the reveal schedule of [`Level::domain_range_time`].
+    pub fn new(level: &Level) -> Self {
+        let mut simulation = Level::new(level.enemy_path.clone(), level.max_time)
+            .expect("The enemy path of a level is valid.");
+        let mut step_at = |time: f64| {
+            simulation.time_taken = time;
+            reveal_step(&simulation)
+        };
+        let steps = (1..=STEPS)
+            .map(|i| step_at(level.max_time * i as f64 / STEPS as f64))
+            .collect::<Vec<_>>();
+        // More points never make the fit worse, so the first determined step
+        // can be refined by bisection with the step before.
+        let earliest_time = steps.iter().position(RevealStep::is_determined).map(|i| {
+            let mut end = steps[i].time;
+            let mut start = if i == 0 { 0.0 } else { steps[i - 1].time };
+            for _ in 0..REFINEMENTS {
+                let middle = (start + end) / 2.0;
+                if step_at(middle).is_determined() {
+                    end = middle;
+                } else {
+                    start = middle;
+                }
+            }
+            end
+        });
+        Self {
+            steps,
+            earliest_time,
+        }
+    }
+
+    /// A level is fair if the path determines the parameters before time runs out.
+    pub fn is_fair(&self) -> bool {
+        self.earliest_time.is_some()
+    }
+}
+
+/// Fits a path of the same family to the points that are revealed at the time of the level.
+fn reveal_step(level: &Level) -> RevealStep {
+    let ts = level.domain_range_time(SPACING).collect::<Vec<_>>();
+    let mut step = RevealStep {
+        time: level.time_taken,
+        points: ts.len(),
+        condition: f64::INFINITY,
+        fit_error: f64::INFINITY,
+        uncertainty: f64::INFINITY,
+    };
+    let params = level.enemy_path.params();
+    if ts.len() < params.len() {
+        return step;
+    }
+    // The path is linearized around its parameters, which is exact for polynomials.
+    let jacobian = level.enemy_path.jacobian(&ts);
+    let ys = jacobian.mul_vec(&params);
+    let qr = Qr::new(&jacobian);
+    step.condition = qr.condition_estimate();
+    if let Some(fit) = qr.solve_least_squares(&ys) {
+        step.fit_error = fit
+            .iter()
+            .zip(&params)
+            .map(|(fit, param)| (fit - param).abs())
+            .fold(0.0, f64::max);
+    }
+    if let Some(norms) = qr.inverse_r_row_norms() {
+        step.uncertainty = READING_ERROR * norms.into_iter().fold(0.0, f64::max);
+    }
+    step
+}
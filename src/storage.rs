@@ -0,0 +1,74 @@
+/// The files are in the `equata` folder of the data directory of the platform.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_path(name: &str) -> Result<std::path::PathBuf, String> {
+    use std::env::var_os;
+    use std::path::PathBuf;
+
+    let data_dir = if cfg!(target_os = "windows") {
+        var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    data_dir
+        .map(|dir| dir.join("equata").join(name))
+        .ok_or_else(|| "Couldn't find the data directory.".to_string())
+}
+
+/// Reads a file that was kept between runs of the game, or `Ok(None)` if it doesn't exist.
+/// Files are named by a relative path like `replays/level1.replay.ron`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    let path = file_path(name)?;
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Couldn't read {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, text: &str) -> Result<(), String> {
+    let path = file_path(name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+    }
+    // Write to another file first, so that a crash can't leave half a file behind.
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, text)
+        .and_then(|()| std::fs::rename(&temp_path, &path))
+        .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+}
+
+/// The files are items in the `localStorage` of the browser, e.g. `profile.ron` is `equata.profile`.
+#[cfg(target_arch = "wasm32")]
+fn storage_key(name: &str) -> String {
+    format!("equata.{}", name.trim_end_matches(".ron"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| "The browser doesn't allow local storage.".to_string())
+}
+
+/// Reads the file, or `Ok(None)` if it doesn't exist.
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    local_storage()?
+        .get_item(&storage_key(name))
+        .map_err(|_| format!("Couldn't read {} from local storage.", name))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, text: &str) -> Result<(), String> {
+    local_storage()?
+        .set_item(&storage_key(name), text)
+        .map_err(|_| format!("Couldn't write {} to local storage.", name))
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::generator::{generate_level, Difficulty, GeneratorSettings, Rng};
+use crate::level::Level;
+use crate::score::LevelResult;
+
+/// The degree of the first levels of a run.
+const START_DEGREE: usize = 2;
+
+/// The highest degree of a run, after which only the time gets tighter.
+const MAX_DEGREE: usize = 6;
+
+/// The number of levels that are cleared at a degree before it goes up.
+const LEVELS_PER_DEGREE: usize = 3;
+
+/// The time limit is multiplied by this for every level that was cleared at the same degree.
+const TIME_FACTOR: f64 = 0.85;
+
+/// The time limit never gets tighter than this, before the time that carries over is added.
+const MIN_TIME: f64 = 15.0;
+
+/// The number of runs that are kept on the leaderboard.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Generated levels back to back, each a bit harder than the one before, until one is lost.
+/// The time that is left of a level carries over to the next one.
+#[derive(Debug, Clone)]
+pub struct SurvivalRun {
+    rng: Rng,
+    pub levels_cleared: usize,
+    /// The sum of the scores of the cleared levels.
+    pub score: u32,
+    /// Time in seconds that was left of the last cleared level, which is added to the next level.
+    pub time_carried: f64,
+    /// A level was lost, so the run is over.
+    pub over: bool,
+    /// The place of the run on the leaderboard, once it is over.
+    pub leaderboard_place: Option<usize>,
+}
+
+impl SurvivalRun {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            levels_cleared: 0,
+            score: 0,
+            time_carried: 0.0,
+            over: false,
+            leaderboard_place: None,
+        }
+    }
+
+    /// The settings of the next level: the degree goes up every few levels,
+    /// and in between the time gets tighter.
+    pub fn settings(&self) -> GeneratorSettings {
+        let degree = (START_DEGREE + self.levels_cleared / LEVELS_PER_DEGREE).min(MAX_DEGREE);
+        let cleared_at_degree = self.levels_cleared - (degree - START_DEGREE) * LEVELS_PER_DEGREE;
+        let mut settings = GeneratorSettings::new(degree, Difficulty::Medium);
+        settings.max_time =
+            (settings.max_time * TIME_FACTOR.powi(cleared_at_degree as i32)).max(MIN_TIME);
+        settings
+    }
+
+    /// Generates the next level, with the time that carries over added to its time limit.
+    pub fn next_level(&mut self) -> Level {
+        let mut level = generate_level(&self.settings(), &mut self.rng);
+        level.max_time += self.time_carried;
+        level
+    }
+
+    /// Adds a level that is over to the run. A lost level ends the run.
+    pub fn finish_level(&mut self, level: &Level) {
+        if !level.won {
+            self.over = true;
+            self.time_carried = 0.0;
+            return;
+        }
+        self.levels_cleared += 1;
+        self.score += LevelResult::new(level).score;
+        self.time_carried = (level.max_time - level.time_taken).max(0.0);
+    }
+
+    pub fn record(&self) -> SurvivalRecord {
+        SurvivalRecord {
+            levels_cleared: self.levels_cleared,
+            score: self.score,
+            degree: self.settings().degree,
+        }
+    }
+}
+
+/// A finished run, as it is kept on the leaderboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurvivalRecord {
+    pub levels_cleared: usize,
+    pub score: u32,
+    /// The degree of the level that ended the run.
+    pub degree: usize,
+}
+
+/// Adds the record to the leaderboard, which is sorted from the highest score down.
+/// Returns the place of the record, or `None` if it didn't make the leaderboard.
+pub fn add_to_leaderboard(
+    leaderboard: &mut Vec<SurvivalRecord>,
+    record: SurvivalRecord,
+) -> Option<usize> {
+    let place = leaderboard
+        .iter()
+        .position(|other| {
+            (record.score, record.levels_cleared) > (other.score, other.levels_cleared)
+        })
+        .unwrap_or(leaderboard.len());
+    if place >= LEADERBOARD_SIZE {
+        return None;
+    }
+    leaderboard.insert(place, record);
+    leaderboard.truncate(LEADERBOARD_SIZE);
+    Some(place)
+}
@@ -0,0 +1,762 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::math::Matrix;
+use crate::polynomial::Polynomial;
+
+/// Precision with which the roots of a path are computed.
+const ROOT_TOLERANCE: f64 = 1e-9;
+
+/// Roots are searched for in `[-ROOT_SEARCH, ROOT_SEARCH]`, unless the family knows better.
+const ROOT_SEARCH: f64 = 10.0;
+
+/// Number of intervals in which sign changes are looked for when searching for roots.
+const ROOT_STEPS: usize = 4000;
+
+/// Number of points at which a path is evaluated to find its extremes.
+const MIN_MAX_SAMPLES: usize = 500;
+
+/// Gravity of [`Projectile`] paths.
+const GRAVITY: f64 = 9.81;
+
+/// A family of curves that the enemy can follow, like polynomials or sinusoids.
+///
+/// Every family has a fixed formula with a list of named parameters,
+/// which the player changes to match the path of the enemy.
+pub trait Trajectory: fmt::Display + Send + Sync {
+    fn family(&self) -> TrajectoryFamily;
+
+    fn eval(&self, x: f64) -> f64;
+
+    fn params(&self) -> &[f64];
+
+    fn params_mut(&mut self) -> &mut [f64];
+
+    fn clone_box(&self) -> Box<dyn Trajectory>;
+
+    /// Names of the parameters, in the same order as [`Trajectory::params`].
+    fn param_names(&self) -> Vec<String> {
+        self.family().param_names(self.params().len())
+    }
+
+    /// The values that the parameter at position `i` can take.
+    fn param_range(&self, i: usize) -> RangeInclusive<f64> {
+        self.family().param_range(i)
+    }
+
+    /// The formula of the path, written with the names of the parameters and `variable` instead of `x`.
+    fn template(&self, variable: &str) -> String {
+        self.family().template(self.params().len(), variable)
+    }
+
+    /// The formula of the path with the values of the parameters, written with `variable` instead of `x`.
+    ///
+    /// By default this is the [`fmt::Display`] of the path, in which every `x` is the variable.
+    fn formula(&self, precision: usize, variable: &str) -> String {
+        format!("{:.*}", precision, self).replace('x', variable)
+    }
+
+    /// Where the enemy path is looked for, see [`Trajectory::roots`].
+    fn root_search_range(&self) -> (f64, f64) {
+        (-ROOT_SEARCH, ROOT_SEARCH)
+    }
+
+    /// The real roots of the path in increasing order.
+    ///
+    /// By default the roots are found where the sign of the path changes,
+    /// so roots where the path only touches zero aren't counted.
+    fn roots(&self) -> Vec<f64> {
+        let (start, end) = self.root_search_range();
+        let step = (end - start) / ROOT_STEPS as f64;
+        let mut roots = Vec::new();
+        // The last point where the path isn't zero, if any.
+        let mut prev: Option<(f64, f64)> = None;
+        // The first of the points since then where the path is exactly zero.
+        let mut zero = None;
+        for i in 0..=ROOT_STEPS {
+            let x = start + step * i as f64;
+            let y = self.eval(x);
+            if y == 0.0 {
+                zero.get_or_insert(x);
+                continue;
+            }
+            match (prev, zero) {
+                // The path isn't known before the range, so zeros at its start count.
+                (None, Some(zero)) => roots.push(zero),
+                (Some((_, prev_y)), Some(zero)) if prev_y * y < 0.0 => roots.push(zero),
+                (Some((prev_x, prev_y)), None) if prev_y * y < 0.0 => {
+                    roots.push(bisect(self, prev_x, x))
+                }
+                _ => {}
+            }
+            prev = Some((x, y));
+            zero = None;
+        }
+        // The same goes for zeros at the end of the range.
+        roots.extend(zero);
+        roots
+    }
+
+    /// The smallest and largest value of the path in `[start, end]`.
+    fn min_max(&self, start: f64, end: f64) -> [f64; 2] {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for i in 0..=MIN_MAX_SAMPLES {
+            let y = self.eval(start + (end - start) * i as f64 / MIN_MAX_SAMPLES as f64);
+            min = min.min(y);
+            max = max.max(y);
+        }
+        [min, max]
+    }
+
+    /// How much the path at each of the points `xs` changes with each parameter,
+    /// one row per point and one column per parameter.
+    ///
+    /// By default this is approximated with central differences.
+    fn jacobian(&self, xs: &[f64]) -> Matrix {
+        let len = self.params().len();
+        let mut matrix = Matrix::zeros(xs.len(), len);
+        let mut shifted = self.clone_box();
+        for j in 0..len {
+            let param = self.params()[j];
+            let h = 1e-6 * param.abs().max(1.0);
+            for (i, x) in xs.iter().enumerate() {
+                shifted.params_mut()[j] = param + h;
+                let above = shifted.eval(*x);
+                shifted.params_mut()[j] = param - h;
+                let below = shifted.eval(*x);
+                matrix[(i, j)] = (above - below) / (2.0 * h);
+            }
+            shifted.params_mut()[j] = param;
+        }
+        matrix
+    }
+
+    /// The path as a polynomial, if it is one.
+    fn as_polynomial(&self) -> Option<&Polynomial> {
+        None
+    }
+
+    /// Sets every parameter to 1, or as close to 1 as its range allows,
+    /// which is where the prediction of the player starts.
+    fn reset(&mut self) {
+        let ranges = (0..self.params().len())
+            .map(|i| self.param_range(i))
+            .collect::<Vec<_>>();
+        for (param, range) in self.params_mut().iter_mut().zip(ranges) {
+            *param = 1.0_f64.clamp(*range.start(), *range.end());
+        }
+    }
+}
+
+impl Clone for Box<dyn Trajectory> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Finds a root between `start` and `end`, where the path has different signs.
+fn bisect(path: &(impl Trajectory + ?Sized), mut start: f64, mut end: f64) -> f64 {
+    let start_sign = path.eval(start).signum();
+    while end - start > ROOT_TOLERANCE {
+        let middle = (start + end) / 2.0;
+        let y = path.eval(middle);
+        if y == 0.0 {
+            return middle;
+        }
+        if y.signum() == start_sign {
+            start = middle;
+        } else {
+            end = middle;
+        }
+    }
+    (start + end) / 2.0
+}
+
+/// The kinds of [`Trajectory`], as stored in level files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrajectoryFamily {
+    #[default]
+    Polynomial,
+    Sinusoid,
+    Exponential,
+    Rational,
+    AbsoluteValue,
+    Projectile,
+}
+
+impl TrajectoryFamily {
+    pub const ALL: [TrajectoryFamily; 6] = [
+        TrajectoryFamily::Polynomial,
+        TrajectoryFamily::Sinusoid,
+        TrajectoryFamily::Exponential,
+        TrajectoryFamily::Rational,
+        TrajectoryFamily::AbsoluteValue,
+        TrajectoryFamily::Projectile,
+    ];
+
+    /// The names of the parameters of the families with a fixed number of parameters.
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            TrajectoryFamily::Polynomial => &[],
+            TrajectoryFamily::Sinusoid => &["amplitude", "frequency", "phase", "offset"],
+            TrajectoryFamily::Exponential => &["scale", "rate", "slope", "offset"],
+            TrajectoryFamily::Rational => &["a", "b", "c", "d"],
+            TrajectoryFamily::AbsoluteValue => &["slope", "center", "height"],
+            TrajectoryFamily::Projectile => &["angle", "speed", "launch"],
+        }
+    }
+
+    /// The number of parameters, or `None` if any number of parameters works.
+    pub fn param_count(self) -> Option<usize> {
+        match self {
+            TrajectoryFamily::Polynomial => None,
+            family => Some(family.names().len()),
+        }
+    }
+
+    /// The names of the parameters of a path with `count` parameters.
+    pub fn param_names(self, count: usize) -> Vec<String> {
+        match self {
+            TrajectoryFamily::Polynomial => (0..count).map(Polynomial::coef_name).collect(),
+            family => family.names().iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// The values that the parameter at position `i` can take.
+    /// Apart from a few physical limits, this is the range of the controls in game.
+    pub fn param_range(self, i: usize) -> RangeInclusive<f64> {
+        match (self, i) {
+            (TrajectoryFamily::Exponential, 1) => -5.0..=5.0,
+            // The denominator must not be zero.
+            (TrajectoryFamily::Rational, 3) => 0.1..=10.0,
+            (TrajectoryFamily::Projectile, 0) => 1.0..=89.0,
+            (TrajectoryFamily::Projectile, 1) => 0.5..=20.0,
+            _ => -10.0..=10.0,
+        }
+    }
+
+    /// The formula of a path with `count` parameters, written with `variable` instead of `x`.
+    pub fn template(self, count: usize, variable: &str) -> String {
+        let v = variable;
+        match self {
+            TrajectoryFamily::Polynomial => Polynomial::template(count.saturating_sub(1), v),
+            TrajectoryFamily::Sinusoid => {
+                format!("amplitude * sin(frequency * {} + phase) + offset", v)
+            }
+            TrajectoryFamily::Exponential => {
+                format!("scale * e^(rate * {}) + slope * {} + offset", v, v)
+            }
+            TrajectoryFamily::Rational => format!("(a{0}^2 + b{0} + c) / ({0}^2 + d)", v),
+            TrajectoryFamily::AbsoluteValue => format!("slope * |{} - center| + height", v),
+            // Projectiles are described in words, with the `x` of the plane.
+            TrajectoryFamily::Projectile => {
+                "launched from x = launch, at angle degrees with speed".into()
+            }
+        }
+    }
+
+    /// The parameters of a simple path of the family, to start a new level with.
+    pub fn example(self) -> Vec<f64> {
+        match self {
+            TrajectoryFamily::Polynomial => vec![-1.0, 0.0, 1.0],
+            TrajectoryFamily::Sinusoid => vec![1.0, 1.0, 0.0, 0.0],
+            TrajectoryFamily::Exponential => vec![1.0, 1.0, -3.0, 0.0],
+            TrajectoryFamily::Rational => vec![-1.0, 0.0, 1.0, 1.0],
+            TrajectoryFamily::AbsoluteValue => vec![-1.0, 0.0, 1.0],
+            TrajectoryFamily::Projectile => vec![45.0, 5.0, 0.0],
+        }
+    }
+
+    /// Creates the path of this family with the given parameters.
+    pub fn build(self, params: &[f64]) -> Result<Box<dyn Trajectory>, String> {
+        if let Some(count) = self.param_count() {
+            if params.len() != count {
+                return Err(format!("{} paths need {} parameters.", self, count));
+            }
+        } else if params.is_empty() {
+            return Err(format!("{} paths need at least 1 parameter.", self));
+        }
+        let names = self.param_names(params.len());
+        for (i, (param, name)) in params.iter().zip(names).enumerate() {
+            let range = self.param_range(i);
+            if !range.contains(param) {
+                return Err(format!(
+                    "The {} must be between {} and {}.",
+                    name,
+                    range.start(),
+                    range.end()
+                ));
+            }
+        }
+        let path: Box<dyn Trajectory> = match self {
+            TrajectoryFamily::Polynomial => Box::new(Polynomial::new(params.iter().copied())),
+            TrajectoryFamily::Sinusoid => {
+                Box::new(Sinusoid([params[0], params[1], params[2], params[3]]))
+            }
+            TrajectoryFamily::Exponential => {
+                Box::new(Exponential([params[0], params[1], params[2], params[3]]))
+            }
+            TrajectoryFamily::Rational => {
+                Box::new(Rational([params[0], params[1], params[2], params[3]]))
+            }
+            TrajectoryFamily::AbsoluteValue => {
+                Box::new(AbsoluteValue([params[0], params[1], params[2]]))
+            }
+            TrajectoryFamily::Projectile => Box::new(Projectile([params[0], params[1], params[2]])),
+        };
+        Ok(path)
+    }
+}
+
+impl fmt::Display for TrajectoryFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TrajectoryFamily::Polynomial => "Polynomial",
+            TrajectoryFamily::Sinusoid => "Sinusoid",
+            TrajectoryFamily::Exponential => "Exponential",
+            TrajectoryFamily::Rational => "Rational",
+            TrajectoryFamily::AbsoluteValue => "Absolute value",
+            TrajectoryFamily::Projectile => "Projectile",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Writes a number with the precision of the formatter, if any.
+fn write_number(f: &mut fmt::Formatter<'_>, value: f64) -> fmt::Result {
+    match f.precision() {
+        Some(precision) => write!(f, "{:.*}", precision, value),
+        None => write!(f, "{}", value),
+    }
+}
+
+/// Writes ` + value` or ` - |value|`, for terms after the first one.
+fn write_term(f: &mut fmt::Formatter<'_>, value: f64) -> fmt::Result {
+    if value < 0.0 {
+        write!(f, " - ")?;
+    } else {
+        write!(f, " + ")?;
+    }
+    write_number(f, value.abs())
+}
+
+impl Trajectory for Polynomial {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::Polynomial
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        Polynomial::eval(self, x)
+    }
+
+    fn params(&self) -> &[f64] {
+        self.coefs()
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        self.coefs_mut()
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+
+    fn roots(&self) -> Vec<f64> {
+        Polynomial::roots(self, ROOT_TOLERANCE)
+            .into_iter()
+            .map(|root| root.x)
+            .collect()
+    }
+
+    fn min_max(&self, start: f64, end: f64) -> [f64; 2] {
+        Polynomial::min_max(self, start, end)
+    }
+
+    fn jacobian(&self, xs: &[f64]) -> Matrix {
+        Matrix::vandermonde(xs, self.len())
+    }
+
+    fn as_polynomial(&self) -> Option<&Polynomial> {
+        Some(self)
+    }
+}
+
+/// The path `amplitude * sin(frequency * x + phase) + offset`.
+///
+/// It has infinitely many roots, so the enemy path is the first arch after `x = 0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sinusoid(pub [f64; 4]);
+
+impl Trajectory for Sinusoid {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::Sinusoid
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let [amplitude, frequency, phase, offset] = self.0;
+        amplitude * (frequency * x + phase).sin() + offset
+    }
+
+    fn params(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+
+    fn root_search_range(&self) -> (f64, f64) {
+        let frequency = self.0[1].abs();
+        if frequency == 0.0 {
+            return (-ROOT_SEARCH, ROOT_SEARCH);
+        }
+        // Two periods always contain a whole arch.
+        (0.0, 4.0 * PI / frequency)
+    }
+}
+
+impl fmt::Display for Sinusoid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [amplitude, frequency, phase, offset] = self.0;
+        write_number(f, amplitude)?;
+        write!(f, "sin(")?;
+        write_number(f, frequency)?;
+        write!(f, "x")?;
+        write_term(f, phase)?;
+        write!(f, ")")?;
+        write_term(f, offset)
+    }
+}
+
+/// The path `scale * e^(rate * x) + slope * x + offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exponential(pub [f64; 4]);
+
+impl Trajectory for Exponential {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::Exponential
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let [scale, rate, slope, offset] = self.0;
+        scale * (rate * x).exp() + slope * x + offset
+    }
+
+    fn params(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+}
+
+impl fmt::Display for Exponential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [scale, rate, slope, offset] = self.0;
+        write_number(f, scale)?;
+        write!(f, "e^(")?;
+        write_number(f, rate)?;
+        write!(f, "x)")?;
+        write_term(f, slope)?;
+        write!(f, "x")?;
+        write_term(f, offset)
+    }
+}
+
+/// The path `(ax^2 + bx + c) / (x^2 + d)` with `d > 0`, so it has no poles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rational(pub [f64; 4]);
+
+impl Trajectory for Rational {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::Rational
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let [a, b, c, d] = self.0;
+        (a * x * x + b * x + c) / (x * x + d)
+    }
+
+    fn params(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+
+    fn roots(&self) -> Vec<f64> {
+        // The roots are those of the numerator.
+        let [a, b, c, _] = self.0;
+        Trajectory::roots(&Polynomial::new([a, b, c]))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        let numerator = Polynomial::new([a, b, c]);
+        match f.precision() {
+            Some(precision) => write!(f, "({:.*}) / (x^2", precision, numerator)?,
+            None => write!(f, "({}) / (x^2", numerator)?,
+        }
+        write_term(f, d)?;
+        write!(f, ")")
+    }
+}
+
+/// The path `slope * |x - center| + height`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbsoluteValue(pub [f64; 3]);
+
+impl Trajectory for AbsoluteValue {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::AbsoluteValue
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let [slope, center, height] = self.0;
+        slope * (x - center).abs() + height
+    }
+
+    fn params(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+
+    fn roots(&self) -> Vec<f64> {
+        let [slope, center, height] = self.0;
+        let distance = -height / slope;
+        if slope == 0.0 || distance <= 0.0 {
+            // Only touching zero at the center doesn't give a path either.
+            return Vec::new();
+        }
+        vec![center - distance, center + distance]
+    }
+}
+
+impl fmt::Display for AbsoluteValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [slope, center, height] = self.0;
+        write_number(f, slope)?;
+        write!(f, "|x")?;
+        write_term(f, -center)?;
+        write!(f, "|")?;
+        write_term(f, height)
+    }
+}
+
+/// The parabola of a projectile launched from `(launch, 0)`,
+/// at `angle` degrees above the horizon with the given `speed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projectile(pub [f64; 3]);
+
+impl Trajectory for Projectile {
+    fn family(&self) -> TrajectoryFamily {
+        TrajectoryFamily::Projectile
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let [angle, speed, launch] = self.0;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let dx = x - launch;
+        dx * sin / cos - GRAVITY * dx * dx / (2.0 * speed * speed * cos * cos)
+    }
+
+    fn params(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn params_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Trajectory> {
+        Box::new(self.clone())
+    }
+
+    fn formula(&self, precision: usize, _variable: &str) -> String {
+        format!("{:.*}", precision, self)
+    }
+
+    fn roots(&self) -> Vec<f64> {
+        let [angle, speed, launch] = self.0;
+        // The angle is less than 90 degrees, so the projectile lands to the right.
+        let range = speed * speed * (2.0 * angle.to_radians()).sin() / GRAVITY;
+        vec![launch, launch + range]
+    }
+}
+
+impl fmt::Display for Projectile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [angle, speed, launch] = self.0;
+        write!(f, "launched from x = ")?;
+        write_number(f, launch)?;
+        write!(f, " at ")?;
+        write_number(f, angle)?;
+        write!(f, " degrees with speed ")?;
+        write_number(f, speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::Curve;
+    use crate::level::Level;
+
+    fn build(family: TrajectoryFamily, params: &[f64]) -> Box<dyn Trajectory> {
+        family
+            .build(params)
+            .unwrap_or_else(|err| panic!("{} {:?}: {}", family, params, err))
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn polynomial() {
+        let path = build(TrajectoryFamily::Polynomial, &[1.0, 0.0, -1.0]);
+        assert_eq!(path.eval(2.0), 3.0);
+        assert_close(&path.roots(), &[-1.0, 1.0]);
+        assert!(TrajectoryFamily::Polynomial.build(&[]).is_err());
+        let err = TrajectoryFamily::Polynomial
+            .build(&[11.0, 0.0])
+            .err()
+            .unwrap();
+        assert_eq!(err, "The a must be between -10 and 10.");
+    }
+
+    #[test]
+    fn sinusoid() {
+        let path = build(TrajectoryFamily::Sinusoid, &[2.0, 0.5, 0.0, 1.0]);
+        assert!((path.eval(PI) - 3.0).abs() < 1e-12);
+        // 2 sin(x / 2) + 1 = 0 where x / 2 = 7π/6 or 11π/6.
+        assert_close(&path.roots()[..2], &[7.0 * PI / 3.0, 11.0 * PI / 3.0]);
+        // The first arch starts at x = 0.
+        let path = build(TrajectoryFamily::Sinusoid, &[1.0, 1.0, 0.0, 0.0]);
+        assert_close(&path.roots()[..3], &[0.0, PI, 2.0 * PI]);
+        let err = TrajectoryFamily::Sinusoid
+            .build(&[1.0, 1.0, 0.0])
+            .err()
+            .unwrap();
+        assert_eq!(err, "Sinusoid paths need 4 parameters.");
+        assert!(TrajectoryFamily::Sinusoid
+            .build(&[1.0, -10.5, 0.0, 0.0])
+            .is_err());
+    }
+
+    #[test]
+    fn exponential() {
+        let path = build(TrajectoryFamily::Exponential, &[1.0, 1.0, -3.0, 0.0]);
+        assert_eq!(path.eval(0.0), 1.0);
+        let roots = path.roots();
+        assert_eq!(roots.len(), 2);
+        for root in roots {
+            assert!(path.eval(root).abs() < 1e-8);
+        }
+        let err = TrajectoryFamily::Exponential
+            .build(&[1.0, 6.0, 0.0, 0.0])
+            .err()
+            .unwrap();
+        assert_eq!(err, "The rate must be between -5 and 5.");
+    }
+
+    #[test]
+    fn rational() {
+        let path = build(TrajectoryFamily::Rational, &[-1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(path.eval(0.0), 1.0);
+        assert_eq!(path.eval(1.0), 0.0);
+        assert_close(&path.roots(), &[-1.0, 1.0]);
+        // The denominator can't be zero.
+        assert!(TrajectoryFamily::Rational
+            .build(&[-1.0, 0.0, 1.0, 0.0])
+            .is_err());
+    }
+
+    #[test]
+    fn absolute_value() {
+        let path = build(TrajectoryFamily::AbsoluteValue, &[-2.0, 1.0, 4.0]);
+        assert_eq!(path.eval(3.0), 0.0);
+        assert_eq!(path.eval(1.0), 4.0);
+        assert_close(&path.roots(), &[-1.0, 3.0]);
+        assert!(build(TrajectoryFamily::AbsoluteValue, &[2.0, 1.0, 4.0])
+            .roots()
+            .is_empty());
+        assert!(TrajectoryFamily::AbsoluteValue
+            .build(&[-2.0, 1.0, f64::NAN])
+            .is_err());
+    }
+
+    #[test]
+    fn projectile() {
+        let path = build(TrajectoryFamily::Projectile, &[45.0, 5.0, -1.0]);
+        let range = 25.0 / GRAVITY;
+        assert_eq!(path.eval(-1.0), 0.0);
+        assert!(path.eval(-1.0 + range).abs() < 1e-12);
+        // At 45 degrees the highest point is at a quarter of the range.
+        assert!((path.eval(-1.0 + range / 2.0) - range / 4.0).abs() < 1e-12);
+        assert_close(&path.roots(), &[-1.0, -1.0 + range]);
+        assert!(TrajectoryFamily::Projectile
+            .build(&[90.0, 5.0, 0.0])
+            .is_err());
+    }
+
+    #[test]
+    fn examples_make_levels() {
+        for family in TrajectoryFamily::ALL {
+            let path = build(family, &family.example());
+            assert!(path.roots().len() >= 2, "{}", family);
+            Level::new(Curve::graph(path), 60.0).unwrap();
+        }
+    }
+
+    #[test]
+    fn touching_zero_is_not_a_root() {
+        // These paths reach zero without crossing it, where a sample can land exactly on zero.
+        let touching = [
+            build(TrajectoryFamily::Sinusoid, &[1.0, 1.0, 0.0, -1.0]),
+            build(TrajectoryFamily::Sinusoid, &[-2.0, 0.5, 1.0, 2.0]),
+            build(TrajectoryFamily::AbsoluteValue, &[1.0, 0.0, 0.0]),
+            build(TrajectoryFamily::AbsoluteValue, &[-1.0, 2.0, 0.0]),
+        ];
+        for path in touching {
+            assert!(path.roots().is_empty(), "{}: {:?}", path, path.roots());
+            let err = Level::new(Curve::graph(path), 60.0).err().unwrap();
+            assert_eq!(err, "Needs at least 2 roots where the path crosses zero.");
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::curve::CurveKind;
+use crate::level::{Level, WIN_TOLERANCE};
+
+/// Number of intervals in which the path is divided to compare paths that aren't polynomials.
+const SAMPLES: usize = 1000;
+
+/// How the prediction of the player is compared to the path of the enemy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WinCriterion {
+    /// The largest difference between a parameter of the prediction and of the enemy path,
+    /// e.g. between the coefficients of polynomials.
+    #[default]
+    Coefficients,
+    /// The largest distance between the prediction and the enemy path at the same parameter, over the whole path.
+    /// For graphs this is the vertical distance.
+    MaxDeviation,
+    /// The root mean square of the same distances over the whole path,
+    /// i.e. the L2 distance scaled to the length of the parameter range.
+    L2Distance,
+    /// The distance between where the prediction and the enemy path land,
+    /// i.e. between the end of the path and the closest root of the prediction.
+    /// Polar curves compare the angles of the roots instead.
+    LandingPoint,
+}
+
+impl WinCriterion {
+    pub const ALL: [WinCriterion; 4] = [
+        WinCriterion::Coefficients,
+        WinCriterion::MaxDeviation,
+        WinCriterion::L2Distance,
+        WinCriterion::LandingPoint,
+    ];
+
+    /// A tolerance that is about as hard as the others for typical levels.
+    pub fn default_tolerance(self) -> f64 {
+        match self {
+            WinCriterion::Coefficients => WIN_TOLERANCE,
+            WinCriterion::MaxDeviation => 0.05,
+            WinCriterion::L2Distance => 0.02,
+            WinCriterion::LandingPoint => 0.02,
+        }
+    }
+
+    /// Measures how far the prediction of the level is from the enemy path.
+    pub fn error(self, level: &Level) -> f64 {
+        let (start, end) = (level.start(), level.end());
+        let (player, enemy) = (&level.player_path, &level.enemy_path);
+        // The graphs of polynomials can be compared exactly, other paths are sampled.
+        let difference = match (player.kind(), player.main_component().as_polynomial()) {
+            (CurveKind::Graph, Some(player)) => enemy
+                .main_component()
+                .as_polynomial()
+                .map(|enemy| player - enemy),
+            _ => None,
+        };
+        match self {
+            WinCriterion::Coefficients => player
+                .params()
+                .iter()
+                .zip(enemy.params())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max),
+            WinCriterion::MaxDeviation => match difference {
+                Some(difference) => {
+                    let [min, max] = difference.min_max(start, end);
+                    min.abs().max(max.abs())
+                }
+                None => distances(level).fold(0.0, f64::max),
+            },
+            WinCriterion::L2Distance => {
+                let mean_square = match difference {
+                    Some(difference) => {
+                        let integral = (&difference * &difference).antiderivative();
+                        (integral.eval(end) - integral.eval(start)) / (end - start)
+                    }
+                    None => distances(level).map(|d| d * d).sum::<f64>() / (SAMPLES + 1) as f64,
+                };
+                // Rounding can make tiny values negative.
+                mean_square.max(0.0).sqrt()
+            }
+            WinCriterion::LandingPoint => {
+                let landing = enemy.point(end);
+                player
+                    .main_component()
+                    .roots()
+                    .iter()
+                    .map(|t| match player.kind() {
+                        // Polar curves always land at the origin, so the direction is compared instead.
+                        CurveKind::Polar => (t - end).abs(),
+                        _ => distance(player.point(*t), landing),
+                    })
+                    .fold(f64::INFINITY, f64::min)
+            }
+        }
+    }
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+/// The distances between the prediction and the enemy path at evenly spaced parameters of the path.
+/// For graphs this is the vertical distance.
+fn distances(level: &Level) -> impl Iterator<Item = f64> + '_ {
+    let (start, end) = (level.start(), level.end());
+    (0..=SAMPLES).map(move |i| {
+        let t = start + (end - start) * i as f64 / SAMPLES as f64;
+        distance(level.player_point(t), level.enemy_point(t))
+    })
+}
+
+impl fmt::Display for WinCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WinCriterion::Coefficients => "Coefficients",
+            WinCriterion::MaxDeviation => "Max deviation",
+            WinCriterion::L2Distance => "L2 distance",
+            WinCriterion::LandingPoint => "Landing point",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// When a prediction is close enough to the enemy path to win the level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WinCondition {
+    pub criterion: WinCriterion,
+    /// The largest error of the criterion that still wins.
+    pub tolerance: f64,
+}
+
+impl WinCondition {
+    pub fn new(criterion: WinCriterion) -> Self {
+        Self {
+            criterion,
+            tolerance: criterion.default_tolerance(),
+        }
+    }
+
+    /// Measures how far the prediction of the level is from the enemy path.
+    pub fn error(&self, level: &Level) -> f64 {
+        self.criterion.error(level)
+    }
+
+    pub fn is_met(&self, level: &Level) -> bool {
+        self.error(level) <= self.tolerance
+    }
+}
+
+impl Default for WinCondition {
+    fn default() -> Self {
+        Self::new(WinCriterion::default())
+    }
+}